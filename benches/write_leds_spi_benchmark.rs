@@ -1,12 +1,74 @@
-use criterion::{criterion_group, criterion_main, Criterion};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use std::io;
 use std::time::Duration;
 
-use rusty_wheels::leds::WheelLEDs;
+use rusty_wheels::clock::MonotonicClock;
+use rusty_wheels::layout::Layout;
+use rusty_wheels::leds::{
+    Apa102Frame, Chipset, FrameEncoder, LedSink, Side, WheelLEDs, Ws2812Frame,
+};
+use rusty_wheels::structs::RGB48;
+
+/// EncodeSink encodes each frame for the wire like SpiSink, but then
+/// throws it away, so that the encoding can be measured without hardware.
+struct EncodeSink {
+    encoded: Apa102Frame,
+}
+
+impl LedSink for EncodeSink {
+    fn write_frame(&mut self, frame: &[RGB48]) -> io::Result<()> {
+        self.encoded.encode(frame);
+        black_box(self.encoded.as_bytes());
+        Ok(())
+    }
+}
+
+fn rainbow<const LEDS: usize>(wheel_leds: &mut WheelLEDs<LEDS>) {
+    for side in wheel_leds.sides() {
+        for led in 0..LEDS {
+            let v = (led * 255 / LEDS) as u8;
+            wheel_leds.set(
+                side,
+                led,
+                (v, 255 - v, if side == Side::LEFT { 64 } else { 0 }),
+            );
+        }
+    }
+}
 
 pub fn criterion_benchmark(c: &mut Criterion) {
-    let mut wheel_leds = WheelLEDs::new();
+    let mut wheel_leds: WheelLEDs<23> = WheelLEDs::new(Layout::default(), Chipset::Apa102);
+
+    c.bench_function("wheel_leds.show()", |b| {
+        b.iter(|| wheel_leds.show(&MonotonicClock))
+    });
+
+    let mut encode_leds: WheelLEDs<23> = WheelLEDs::with_sink(
+        Layout::default(),
+        Box::new(EncodeSink {
+            encoded: Apa102Frame::new(),
+        }),
+    );
+    rainbow(&mut encode_leds);
+
+    c.bench_function("wheel_leds.show() encoding only", |b| {
+        b.iter(|| encode_leds.show(&MonotonicClock))
+    });
+
+    let frame: Vec<RGB48> = (0..46u16)
+        .map(|n| (n * 1400, 65535 - n * 1400, 300))
+        .collect();
+    let mut encoded = Apa102Frame::new();
+
+    c.bench_function("Apa102Frame::encode", |b| {
+        b.iter(|| encoded.encode(black_box(&frame)))
+    });
+
+    let mut encoded = Ws2812Frame::new(false);
 
-    c.bench_function("wheel_leds.show()", |b| b.iter(|| wheel_leds.show()));
+    c.bench_function("Ws2812Frame::encode", |b| {
+        b.iter(|| encoded.encode(black_box(&frame)))
+    });
 }
 
 criterion_group! {
@@ -0,0 +1,346 @@
+use std::env;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, Frame, RgbImage, RgbaImage};
+
+use rusty_wheels::buttons::Button;
+use rusty_wheels::clock::{Clock, ManualClock};
+use rusty_wheels::config::Config;
+use rusty_wheels::engine::{Engine, EngineInputs, ForcedMode};
+use rusty_wheels::leds::{CaptureSink, WheelLEDs};
+use rusty_wheels::magnet::{MagnetLayout, RotationSensor};
+use rusty_wheels::moving_modes::modes;
+use rusty_wheels::pov::PovWheel;
+use rusty_wheels::structs::RGB24;
+use rusty_wheels::timing::FrameSchedule;
+
+/// The number of angular steps the simulated eye can distinguish
+const ANGLE_BINS: usize = 720;
+
+/// The fraction of the wheel radius inside the innermost LED
+const HUB_FRACTION: f32 = 0.2;
+
+/// The simulated time between animated GIF frames
+const GIF_FRAME_MS: u64 = 100;
+
+struct Options {
+    /// simulate only this mode, rather than all of them
+    mode: Option<usize>,
+
+    /// wheel speed at the start of the simulation
+    rpm: f64,
+
+    /// change in wheel speed, in rpm per second
+    accel: f64,
+
+    /// simulated duration for each mode
+    seconds: f64,
+
+    /// rate at which the simulated main loop shows frames
+    fps: f64,
+
+    /// time for the simulated eye to forget most of what it saw
+    persistence: f64,
+
+    /// width and height of the image of each side of the wheel
+    size: usize,
+
+    out: PathBuf,
+
+    /// seed for the first mode's random numbers, rather than a random one
+    seed: Option<u64>,
+}
+
+fn parse_options() -> Options {
+    let mut options = Options {
+        mode: None,
+        rpm: 200.0,
+        accel: 0.0,
+        seconds: 5.0,
+        fps: 2000.0,
+        persistence: 0.1,
+        size: 400,
+        out: PathBuf::from("simulation"),
+        seed: None,
+    };
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = args
+            .next()
+            .unwrap_or_else(|| panic!("{arg} needs a value on command line"));
+        match arg.as_str() {
+            "--mode" => options.mode = Some(value.parse().expect("parseable mode")),
+            "--rpm" => options.rpm = value.parse().expect("parseable rpm"),
+            "--accel" => options.accel = value.parse().expect("parseable acceleration"),
+            "--seconds" => options.seconds = value.parse().expect("parseable seconds"),
+            "--fps" => options.fps = value.parse().expect("parseable fps"),
+            "--persistence" => {
+                let ms: f64 = value.parse().expect("parseable persistence");
+                options.persistence = ms / 1000.0;
+            }
+            "--size" => options.size = value.parse().expect("parseable size"),
+            "--out" => options.out = PathBuf::from(value),
+            "--seed" => options.seed = Some(value.parse().expect("parseable seed")),
+            _ => panic!("unknown command line option {arg}"),
+        }
+    }
+
+    options
+}
+
+/// A SyntheticWheel stands in for the real wheel and magnet: it starts
+/// at a given speed and then changes speed at a constant rate, stopping
+/// rather than reversing if it slows down to nothing.
+#[derive(Clone, Copy)]
+struct SyntheticWheel {
+    rpm: f64,
+    accel: f64,
+}
+
+impl SyntheticWheel {
+    /// The number of revolutions made by time t
+    fn revolutions(&self, t: Duration) -> f64 {
+        let v = self.rpm / 60.0;
+        let a = self.accel / 60.0;
+        let mut s = t.as_secs_f64();
+        if a < 0.0 {
+            s = s.min(-v / a);
+        }
+        v * s + 0.5 * a * s * s
+    }
+
+    /// The time at which the wheel reaches position, in revolutions, or
+    /// None if it stops before getting there
+    fn time_of(&self, position: f64) -> Option<Duration> {
+        let v = self.rpm / 60.0;
+        let a = self.accel / 60.0;
+        let s = if a == 0.0 {
+            position / v
+        } else {
+            let discriminant = v * v + 2.0 * a * position;
+            if discriminant < 0.0 {
+                return None;
+            }
+            (discriminant.sqrt() - v) / a
+        };
+        if s.is_finite() && s >= 0.0 {
+            Some(Duration::from_secs_f64(s))
+        } else {
+            None
+        }
+    }
+}
+
+/// SyntheticMagnet pulses as each magnet passes on a SyntheticWheel,
+/// timestamped with when it passed, as the kernel does
+struct SyntheticMagnet {
+    wheel: SyntheticWheel,
+    magnets: MagnetLayout,
+    clock: ManualClock,
+
+    /// when the wheel was at position 0
+    start: Instant,
+
+    /// the position, in revolutions, of the magnet which last pulsed
+    pulsed_position: f64,
+}
+
+impl RotationSensor for SyntheticMagnet {
+    fn next_pulse(&mut self) -> Option<Instant> {
+        let position = self.magnets.next_position(self.pulsed_position);
+        let time = self.start + self.wheel.time_of(position)?;
+        if time <= self.clock.now() {
+            self.pulsed_position = position;
+            Some(time)
+        } else {
+            None
+        }
+    }
+}
+
+/// A button which is never pressed
+struct NoButton;
+
+impl Button for NoButton {
+    fn pulsed(&mut self) -> bool {
+        false
+    }
+}
+
+fn main() {
+    println!("Starting rusty-wheels simulator");
+
+    let options = parse_options();
+
+    let config = match Config::load() {
+        Ok(c) => c,
+        Err(e) => panic!("config loading returned an error: {}", e),
+    };
+
+    fs::create_dir_all(&options.out).expect("creatable output directory");
+
+    // modes are built for the LED counts of the wheels we have
+    match config.layout.max_leds() {
+        36 => simulate_modes::<36>(&config, &options),
+        _ => simulate_modes::<23>(&config, &options),
+    }
+
+    println!("Ending rusty-wheels simulator");
+}
+
+fn simulate_modes<const LEDS: usize>(config: &Config, options: &Options) {
+    let mode_indices: Vec<usize> = match options.mode {
+        Some(m) => vec![m],
+        None => (0..modes::<LEDS>().len()).collect(),
+    };
+
+    for mode_index in mode_indices {
+        match simulate::<LEDS>(mode_index, config, options) {
+            Ok(_) => (),
+            Err(e) => println!("simulating mode {mode_index} returned an error: {e}"),
+        }
+    }
+}
+
+/// Runs one mode on an Engine, as the main loop does, driven by a
+/// synthetic wheel on a clock which moves on by a frame at a time rather
+/// than in real time, and writes what the eye would see of it.
+fn simulate<const LEDS: usize>(
+    mode_index: usize,
+    config: &Config,
+    options: &Options,
+) -> io::Result<()> {
+    println!("Simulating mode {mode_index}");
+
+    let layout = &config.layout;
+
+    let capture = CaptureSink::new();
+    let wheel_leds: WheelLEDs<LEDS> =
+        WheelLEDs::with_sink(layout.clone(), Box::new(capture.clone()));
+
+    let wheel = SyntheticWheel {
+        rpm: options.rpm,
+        accel: options.accel,
+    };
+
+    let start = Instant::now();
+    let clock = ManualClock::new(start);
+
+    let inputs = EngineInputs {
+        magnet: Box::new(SyntheticMagnet {
+            wheel,
+            magnets: config.magnets.clone(),
+            clock: clock.clone(),
+            start,
+            pulsed_position: 0.0,
+        }),
+        push_button: Box::new(NoButton),
+        second_button: Box::new(NoButton),
+        clock: Box::new(clock.clone()),
+    };
+
+    let mut engine = Engine::new(
+        inputs,
+        wheel_leds,
+        config.magnets.clone(),
+        config.wheel,
+        Some(ForcedMode::Index(mode_index)),
+        options.seed,
+    )?;
+    // frames come at the simulated frame rate, not the bike's schedule
+    engine.set_schedule(FrameSchedule::default());
+    capture.take_frames();
+
+    let frame_time = Duration::from_secs_f64(1.0 / options.fps);
+    let fade = (-frame_time.as_secs_f64() / options.persistence).exp() as f32;
+
+    let mut pov = PovWheel::new(layout.clone(), ANGLE_BINS);
+
+    let end_time = Duration::from_secs_f64(options.seconds);
+    let gif_frame_time = Duration::from_millis(GIF_FRAME_MS);
+    let still_time = Duration::from_secs(1);
+
+    let mut now = Duration::ZERO;
+
+    let mut next_gif_time = gif_frame_time;
+    let mut next_still_time = still_time;
+    let mut gif_frames = Vec::new();
+
+    while now < end_time {
+        clock.set(start + now);
+        engine.frame()?;
+
+        // each frame stays lit until the next one is shown
+        let next_now = now + frame_time;
+        let revs = wheel.revolutions(now);
+        let from = revs.rem_euclid(1.0);
+        let to = from + (wheel.revolutions(next_now) - revs);
+
+        for frame in capture.take_frames() {
+            pov.fade(fade);
+            pov.sweep(from, to, &frame);
+        }
+
+        if next_now >= next_gif_time {
+            next_gif_time += gif_frame_time;
+            let image = render_image(&pov, options.size);
+            gif_frames.push(Frame::from_parts(
+                RgbaImage::from_fn(image.width(), image.height(), |x, y| {
+                    let [r, g, b] = image.get_pixel(x, y).0;
+                    image::Rgba([r, g, b, 255])
+                }),
+                0,
+                0,
+                Delay::from_numer_denom_ms(GIF_FRAME_MS as u32, 1),
+            ));
+        }
+
+        if next_now >= next_still_time {
+            next_still_time += still_time;
+            let image = render_image(&pov, options.size);
+            let path = options.out.join(format!(
+                "mode-{:02}-{:02}s.png",
+                mode_index,
+                next_now.as_secs()
+            ));
+            image.save(&path).map_err(to_io_error)?;
+            println!("Wrote {}", path.display());
+        }
+
+        now = next_now;
+    }
+
+    let path = options.out.join(format!("mode-{mode_index:02}.gif"));
+    let mut encoder = GifEncoder::new(File::create(&path)?);
+    encoder.set_repeat(Repeat::Infinite).map_err(to_io_error)?;
+    encoder.encode_frames(gif_frames).map_err(to_io_error)?;
+    println!("Wrote {}", path.display());
+
+    Ok(())
+}
+
+/// Draws both faces of the wheel next to each other, left face first,
+/// each as seen from its own side of the bike.
+fn render_image(pov: &PovWheel, size: usize) -> RgbImage {
+    let (left_pixels, right_pixels) = pov.render(size, HUB_FRACTION);
+
+    RgbImage::from_fn((size * 2) as u32, size as u32, |x, y| {
+        let (x, y) = (x as usize, y as usize);
+        let (r, g, b): RGB24 = if x < size {
+            left_pixels[y * size + x]
+        } else {
+            right_pixels[y * size + x - size]
+        };
+        image::Rgb([r, g, b])
+    })
+}
+
+fn to_io_error(e: image::ImageError) -> io::Error {
+    io::Error::other(e)
+}
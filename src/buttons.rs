@@ -1,33 +1,68 @@
+use std::io;
 use std::time::{Duration, Instant};
-use sysfs_gpio::{Direction, Edge, Pin};
 
-// debounce duration is between main calling "pulsed", not when
-// the system detects actual rising edges.
+use crate::gpio::{EdgeLine, GpioLine};
+
+// debounce duration is between the rising edges of accepted presses,
+// as timestamped by the line.
 const DEBOUNCE_DURATION: Duration = Duration::from_secs(1);
 
-// this is the same as Magnet now but maybe I will want
-// eg debounce beheviour or other mode behaviour in here?
+/// The GPIO that the push button is wired to
+const BUTTON_GPIO: u32 = 12;
+
+/// The GPIO that the second push button is wired to
+const SECOND_BUTTON_GPIO: u32 = 13;
+
+/// A Button reports presses of one of the push buttons. PushButton is the
+/// real one; other implementations stand in for it off-bike.
+pub trait Button {
+    /// returns true if the button has been pressed since the previous call
+    fn pulsed(&mut self) -> bool;
+}
 
 pub struct PushButton {
-    poller: sysfs_gpio::PinPoller,
-    debounce_start: Instant,
+    line: Box<dyn EdgeLine>,
+    /// when the previous accepted press happened, if there has been one
+    debounce_start: Option<Instant>,
 }
 
 impl PushButton {
-    pub fn new() -> std::result::Result<PushButton, sysfs_gpio::Error> {
-        let poller = setup_buttons()?;
-        Ok(PushButton {
-            poller,
-            debounce_start: Instant::now(),
-        })
+    pub fn new() -> io::Result<PushButton> {
+        println!("Configuring push button(s)");
+        let line = GpioLine::new(BUTTON_GPIO, "rusty-wheels button")?;
+        println!("Done configuring button(s)");
+
+        Ok(PushButton::with_line(Box::new(line)))
+    }
+
+    /// The second button, which is only used by the calibrate mode
+    pub fn second() -> io::Result<PushButton> {
+        println!("Configuring second push button");
+        let line = GpioLine::new(SECOND_BUTTON_GPIO, "rusty-wheels second button")?;
+        println!("Done configuring second button");
+
+        Ok(PushButton::with_line(Box::new(line)))
+    }
+
+    /// A PushButton which reads the given line, such as a FakeLine
+    pub fn with_line(line: Box<dyn EdgeLine>) -> PushButton {
+        PushButton {
+            line,
+            debounce_start: None,
+        }
     }
+}
 
-    pub fn pulsed(&mut self) -> bool {
-        match self.poller.poll(0) {
-            Ok(Some(value)) => {
-                if Instant::now() - self.debounce_start > DEBOUNCE_DURATION {
-                    println!("Poll got a value {value} which will be ignored");
-                    self.debounce_start = Instant::now();
+impl Button for PushButton {
+    fn pulsed(&mut self) -> bool {
+        match self.line.wait_edge(Some(Duration::ZERO)) {
+            Ok(Some(time)) => {
+                let since = self
+                    .debounce_start
+                    .map(|start| time.saturating_duration_since(start));
+                if since.map(|s| s > DEBOUNCE_DURATION).unwrap_or(true) {
+                    println!("Push button pressed");
+                    self.debounce_start = Some(time);
                     true
                 } else {
                     println!("Ignoring pulse because of debounce of previous pulse");
@@ -39,19 +74,42 @@ impl PushButton {
     }
 }
 
-fn setup_buttons() -> std::result::Result<sysfs_gpio::PinPoller, sysfs_gpio::Error> {
-    println!("Configuring push button(s)");
-    let pin = Pin::new(12); // other button is 13
-    pin.export()?;
-    pin.set_direction(Direction::In)?;
-    pin.set_edge(Edge::RisingEdge)?;
-    let mut poller: sysfs_gpio::PinPoller = pin.get_poller()?;
-    println!("Making first pin poll");
-    match poller.poll(0)? {
-        Some(value) => println!("Poll got first value {value} - ignoring"),
-        None => (),
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::gpio::FakeLine;
+
+    #[test]
+    fn presses_within_the_debounce_of_the_last_are_ignored() {
+        let (line, edges) = FakeLine::new();
+        let mut button = PushButton::with_line(Box::new(line));
+        let start = Instant::now();
+
+        assert!(!button.pulsed());
+
+        edges.send(start).unwrap();
+        assert!(button.pulsed());
+
+        edges.send(start + Duration::from_millis(500)).unwrap();
+        assert!(!button.pulsed());
+
+        // timed from the last accepted press, not the ignored one
+        edges.send(start + Duration::from_millis(1200)).unwrap();
+        assert!(button.pulsed());
     }
-    println!("Done configuring button(s)");
 
-    Ok(poller)
+    #[test]
+    fn debounce_is_timed_on_when_the_edges_happened() {
+        let (line, edges) = FakeLine::new();
+        let mut button = PushButton::with_line(Box::new(line));
+        let start = Instant::now();
+
+        // read straight after each other, but two seconds apart on the line
+        edges.send(start).unwrap();
+        edges.send(start + Duration::from_secs(2)).unwrap();
+        assert!(button.pulsed());
+        assert!(button.pulsed());
+        assert!(!button.pulsed());
+    }
 }
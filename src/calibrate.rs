@@ -0,0 +1,87 @@
+use std::io;
+
+use crate::config::Config;
+use crate::leds::{Side, WheelLEDs};
+use crate::structs::FrameState;
+
+/// How far each press of a button moves the line, in revolutions
+const OFFSET_STEP: f32 = 1.0 / 120.0;
+
+/// The width of the line, in revolutions
+const LINE_WIDTH: f32 = 0.01;
+
+/// Where the line is drawn: straight up
+const TARGET_ANGLE: f32 = 0.0;
+
+/// A Calibrator runs the calibrate mode, for setting each arm's
+/// angle_offset. The arm being calibrated draws a line out from the hub
+/// where it thinks straight up is, with a dim ring at the hub for each arm
+/// before it in the layout, and then one more. While the wheel turns, the
+/// first button moves the line a little way round in the direction of
+/// rotation and the second moves it back; while it is stopped, the second
+/// moves on to the next arm. Each change is saved to the config file.
+pub struct Calibrator {
+    side: Side,
+}
+
+impl Default for Calibrator {
+    fn default() -> Calibrator {
+        Calibrator::new()
+    }
+}
+
+impl Calibrator {
+    pub fn new() -> Calibrator {
+        Calibrator { side: Side(0) }
+    }
+
+    /// Moves the line on the arm being calibrated by steps, forwards in
+    /// the direction of rotation or backwards if negative, and saves the
+    /// new offset. Failing to save is reported but not an error, so the
+    /// offset can still be read off the console.
+    pub fn nudge<const LEDS: usize>(&mut self, wheel_leds: &mut WheelLEDs<LEDS>, steps: i32) {
+        let offset = wheel_leds.layout().arm(self.side).angle_offset;
+
+        // kept between -0.5 and 0.5, the smallest correction
+        let offset = (offset - steps as f32 * OFFSET_STEP + 0.5).rem_euclid(1.0) - 0.5;
+        println!("Arm {} angle_offset is now {:.4}", self.side.0, offset);
+
+        wheel_leds.set_angle_offset(self.side, offset);
+        if let Err(e) = Config::save_angle_offsets(wheel_leds.layout()) {
+            println!("Calibration not saved: {e}");
+        }
+    }
+
+    /// Moves on to calibrating the next arm
+    pub fn next_arm<const LEDS: usize>(&mut self, wheel_leds: &WheelLEDs<LEDS>) {
+        let arms = wheel_leds.layout().arms.len();
+        self.side = Side((self.side.0 + 1) % arms);
+        println!("Calibrating arm {}", self.side.0);
+    }
+
+    pub fn render<const LEDS: usize>(
+        &self,
+        wheel_leds: &mut WheelLEDs<LEDS>,
+        framestate: &FrameState,
+    ) -> io::Result<()> {
+        let distance = (framestate.angle(self.side) - TARGET_ANGLE).rem_euclid(1.0);
+        let on_line = distance.min(1.0 - distance) < LINE_WIDTH / 2.0;
+
+        for side in wheel_leds.sides() {
+            for led in 0..LEDS {
+                let colour = if side != self.side {
+                    (0, 0, 0)
+                } else if on_line {
+                    (255, 255, 255)
+                } else if led <= side.0 {
+                    (32, 32, 32)
+                } else {
+                    (0, 0, 0)
+                };
+                wheel_leds.set(side, led, colour);
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -1,12 +1,14 @@
 use std::io;
 
-use crate::helpers::fraction_to_rgb;
+use crate::helpers::{fraction_to_rgb48, leds_between};
 use crate::leds::{Side, WheelLEDs};
 use crate::structs::{FrameState, StatelessStoppedMode};
 
-const MODE_CHANGE_SEC: u64 = 60;
+/// How long each of the chill modes is shown before the next
+pub const MODE_CHANGE_SEC: u64 = 60;
 
-fn chill_modes<const LEDS: usize>() -> &'static [StatelessStoppedMode<LEDS>] {
+/// The chill modes, in the order they take turns
+pub fn chill_modes<const LEDS: usize>() -> &'static [StatelessStoppedMode<LEDS>] {
     &[rainbow, complement_sides, complement_alternates, rgb]
 }
 
@@ -19,8 +21,9 @@ pub fn render_chill_mode<const LEDS: usize>(
 
     let mode = modes[t];
 
-    mode(Side::Left, wheel_leds, framestate)?;
-    mode(Side::Right, wheel_leds, framestate)?;
+    for side in wheel_leds.sides() {
+        mode(side, wheel_leds, framestate)?;
+    }
     Ok(())
 }
 
@@ -37,8 +40,8 @@ fn rainbow<const LEDS: usize>(
     for led in 0..LEDS {
         let pos_phase = (led as f32) / (LEDS as f32);
         let phase = (pos_phase + time_phase) % 1.0;
-        let rgb = fraction_to_rgb(phase, Some(0.5));
-        wheel_leds.set(side, led, rgb);
+        let rgb = fraction_to_rgb48(phase, Some(0.5));
+        wheel_leds.set16(side, led, rgb);
     }
 
     Ok(())
@@ -49,7 +52,7 @@ fn complement_sides<const LEDS: usize>(
     wheel_leds: &mut WheelLEDs<LEDS>,
     framestate: &FrameState,
 ) -> io::Result<()> {
-    let side_phase = if side == Side::Left { 0.0 } else { 0.5 };
+    let side_phase = if side == Side::LEFT { 0.0 } else { 0.5 };
 
     let now_ms = framestate.now.as_millis();
     let now_steps = (now_ms as f32) / 30000.0;
@@ -58,10 +61,10 @@ fn complement_sides<const LEDS: usize>(
 
     let phase = (time_phase + side_phase) % 1.0;
 
-    let rgb = fraction_to_rgb(phase, Some(0.5));
+    let rgb = fraction_to_rgb48(phase, Some(0.5));
 
     for led in 0..LEDS {
-        wheel_leds.set(side, led, rgb);
+        wheel_leds.set16(side, led, rgb);
     }
 
     Ok(())
@@ -72,7 +75,7 @@ fn complement_alternates<const LEDS: usize>(
     wheel_leds: &mut WheelLEDs<LEDS>,
     framestate: &FrameState,
 ) -> io::Result<()> {
-    let side_phase = if side == Side::Left { 0.0 } else { 0.5 };
+    let side_phase = if side == Side::LEFT { 0.0 } else { 0.5 };
 
     let now_ms = framestate.now.as_millis();
     let now_steps = (now_ms as f32) / 30000.0;
@@ -82,8 +85,8 @@ fn complement_alternates<const LEDS: usize>(
     for led in 0..LEDS {
         let pos_phase = if (led / 3) % 2 == 0 { 0.0 } else { 0.5 };
         let phase = (time_phase + side_phase + pos_phase) % 1.0;
-        let rgb = fraction_to_rgb(phase, Some(0.25));
-        wheel_leds.set(side, led, rgb);
+        let rgb = fraction_to_rgb48(phase, Some(0.25));
+        wheel_leds.set16(side, led, rgb);
     }
 
     Ok(())
@@ -97,10 +100,6 @@ fn sawtooth(x: f32) -> f32 {
     }
 }
 
-fn gamma(x: f32) -> f32 {
-    x.powf(2.5)
-}
-
 fn rgb<const LEDS: usize>(
     side: Side,
     wheel_leds: &mut WheelLEDs<LEDS>,
@@ -109,29 +108,33 @@ fn rgb<const LEDS: usize>(
     let now_ms = framestate.now.as_millis();
 
     let r_steps = (now_ms as f32) / 19000.0;
-    let r_phase = gamma(sawtooth(r_steps % 1.0));
-    let r = (r_phase * 255.0) as u8;
+    let r_phase = sawtooth(r_steps % 1.0);
+    let r = (r_phase * 65535.0) as u16;
 
-    for led in 0..7 {
-        wheel_leds.set(side, led, (r, 0, 0));
+    for led in leds_between(0.0, 0.3, LEDS) {
+        wheel_leds.set16(side, led, (r, 0, 0));
     }
 
-    wheel_leds.set(side, 7, (0, 0, 0));
+    for led in leds_between(0.3, 0.35, LEDS) {
+        wheel_leds.set(side, led, (0, 0, 0));
+    }
     let g_steps = (now_ms as f32) / 23836.0;
-    let g_phase = gamma(sawtooth(g_steps % 1.0));
-    let g = (g_phase * 255.0) as u8;
+    let g_phase = sawtooth(g_steps % 1.0);
+    let g = (g_phase * 65535.0) as u16;
 
-    for led in 8..15 {
-        wheel_leds.set(side, led, (0, g, 0));
+    for led in leds_between(0.35, 0.65, LEDS) {
+        wheel_leds.set16(side, led, (0, g, 0));
     }
 
-    wheel_leds.set(side, 15, (0, 0, 0));
+    for led in leds_between(0.65, 0.7, LEDS) {
+        wheel_leds.set(side, led, (0, 0, 0));
+    }
     let b_steps = (now_ms as f32) / 27777.0;
-    let b_phase = gamma(sawtooth(b_steps % 1.0));
-    let b = (b_phase * 255.0) as u8;
+    let b_phase = sawtooth(b_steps % 1.0);
+    let b = (b_phase * 65535.0) as u16;
 
-    for led in 16..23 {
-        wheel_leds.set(side, led, (0, 0, b));
+    for led in leds_between(0.7, 1.0, LEDS) {
+        wheel_leds.set16(side, led, (0, 0, b));
     }
 
     Ok(())
@@ -0,0 +1,77 @@
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long before a deadline to stop sleeping and spin instead, as sleeps
+/// can run over by more than a column takes at speed
+const SPIN_MARGIN: Duration = Duration::from_micros(200);
+
+/// A Clock tells the Engine, and through FrameState the modes, the time,
+/// and waits for it. MonotonicClock is the real one; ManualClock is moved
+/// on by hand, for running frame by frame under test, or faster than real
+/// time.
+pub trait Clock {
+    fn now(&self) -> Instant;
+
+    /// Returns at deadline, or straight away if it has passed
+    fn sleep_until(&self, deadline: Instant);
+}
+
+/// MonotonicClock reads the system's monotonic clock, the same one that
+/// magnet pulses are timestamped with.
+pub struct MonotonicClock;
+
+impl Clock for MonotonicClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    /// Sleeps until shortly before deadline, then spins until it
+    fn sleep_until(&self, deadline: Instant) {
+        let now = Instant::now();
+        if deadline > now + SPIN_MARGIN {
+            thread::sleep(deadline - now - SPIN_MARGIN);
+        }
+        while Instant::now() < deadline {
+            std::hint::spin_loop();
+        }
+    }
+}
+
+/// ManualClock only changes time when told to. Clones share the same time,
+/// so one can be given to an Engine and another kept to move it on.
+/// Sleeping moves the time on to the deadline without waiting.
+#[derive(Clone)]
+pub struct ManualClock {
+    now: Arc<Mutex<Instant>>,
+}
+
+impl ManualClock {
+    pub fn new(start: Instant) -> ManualClock {
+        ManualClock {
+            now: Arc::new(Mutex::new(start)),
+        }
+    }
+
+    /// Moves the time on to now. Time never goes backwards, so an earlier
+    /// time is ignored.
+    pub fn set(&self, now: Instant) {
+        let mut time = self.now.lock().expect("clock lock");
+        *time = (*time).max(now);
+    }
+
+    pub fn advance(&self, by: Duration) {
+        let mut time = self.now.lock().expect("clock lock");
+        *time += by;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        *self.now.lock().expect("clock lock")
+    }
+
+    fn sleep_until(&self, deadline: Instant) {
+        self.set(deadline);
+    }
+}
@@ -0,0 +1,362 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+use crate::layout::Layout;
+use crate::leds::Chipset;
+use crate::magnet::MagnetLayout;
+use crate::rotation::WheelLimits;
+
+/// The configuration file read when RUSTY_WHEELS_CONFIG is not set
+const DEFAULT_CONFIG_FILE: &str = "wheel.toml";
+
+/// Config holds the settings that differ from one wheel to another,
+/// loaded from a TOML file so that they can be changed without a rebuild.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// the kind of LEDs on the strip
+    pub chipset: Chipset,
+
+    /// the angles of the magnets that pass the sensor
+    pub magnets: MagnetLayout,
+
+    /// the size of the wheel and how fast it can go, for telling magnet
+    /// pulses from glitches
+    pub wheel: WheelLimits,
+
+    /// one [[arm]] table for each arm of LEDs
+    #[serde(rename = "arm")]
+    pub layout: Layout,
+}
+
+impl Config {
+    /// The path of the configuration file: the RUSTY_WHEELS_CONFIG
+    /// environment variable, or wheel.toml in the working directory.
+    pub fn path() -> PathBuf {
+        match env::var("RUSTY_WHEELS_CONFIG") {
+            Ok(p) => PathBuf::from(p),
+            Err(_) => PathBuf::from(DEFAULT_CONFIG_FILE),
+        }
+    }
+
+    /// Loads the configuration file, falling back to the built-in
+    /// defaults if there is no file.
+    pub fn load() -> io::Result<Config> {
+        let path = Config::path();
+
+        let text = match fs::read_to_string(&path) {
+            Ok(t) => t,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                println!("No config file at {} - using defaults", path.display());
+                return Ok(Config::default());
+            }
+            Err(e) => return Err(e),
+        };
+
+        println!("Loading config from {}", path.display());
+        let config: Config =
+            toml::from_str(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        config.layout.validate()?;
+        config.magnets.validate()?;
+        config.wheel.validate()?;
+
+        Ok(config)
+    }
+
+    /// Saves the angle_offset of each arm to the configuration file. Only
+    /// those settings are changed, so the rest of the file, comments and
+    /// all, stays as it was. The file is replaced in one go, so losing
+    /// power part way through leaves the old file.
+    pub fn save_angle_offsets(layout: &Layout) -> io::Result<()> {
+        let path = Config::path();
+
+        let text = match fs::read_to_string(&path) {
+            Ok(t) => t,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("no config file at {} to save to", path.display()),
+                ));
+            }
+            Err(e) => return Err(e),
+        };
+
+        let offsets: Vec<f32> = layout.arms.iter().map(|a| a.angle_offset).collect();
+        let updated = set_angle_offsets(&text, &offsets)?;
+
+        let mut temp = path.clone().into_os_string();
+        temp.push(".new");
+        fs::write(&temp, updated)?;
+        fs::rename(&temp, &path)?;
+
+        println!("Saved angle offsets {:?} to {}", offsets, path.display());
+        Ok(())
+    }
+}
+
+/// Rewrites the text of a configuration file with an angle_offset setting
+/// at the top of each [[arm]] table, in place of any it already had.
+fn set_angle_offsets(text: &str, offsets: &[f32]) -> io::Result<String> {
+    let mut lines: Vec<String> = Vec::new();
+    let mut arms = 0;
+    let mut in_arm = false;
+
+    for line in text.lines() {
+        let code = line.split('#').next().unwrap_or("").trim();
+
+        if is_table_header(code) {
+            in_arm = code.replace(' ', "") == "[[arm]]";
+            lines.push(line.to_string());
+            if in_arm {
+                if let Some(offset) = offsets.get(arms) {
+                    lines.push(format!("angle_offset = {offset:.4}"));
+                }
+                arms += 1;
+            }
+            continue;
+        }
+
+        let is_offset = code
+            .strip_prefix("angle_offset")
+            .map(|rest| rest.trim_start().starts_with('='))
+            .unwrap_or(false);
+        if !(in_arm && is_offset) {
+            lines.push(line.to_string());
+        }
+    }
+
+    if arms != offsets.len() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "config file has {arms} [[arm]] tables, but the wheel has {} arms",
+                offsets.len()
+            ),
+        ));
+    }
+
+    let mut updated = lines.join("\n");
+    updated.push('\n');
+    Ok(updated)
+}
+
+/// True if code, a line without its comment, starts a table like [wheel]
+/// or [[arm]], rather than continuing an array over several lines
+fn is_table_header(code: &str) -> bool {
+    if !(code.starts_with('[') && code.ends_with(']')) {
+        return false;
+    }
+    let name = code.trim_matches(|c| c == '[' || c == ']').trim();
+    name.starts_with(|c: char| c.is_alphabetic())
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || "_-. ".contains(c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The angle_offset of each arm in the text of a configuration file
+    fn offsets_in(text: &str) -> Vec<f32> {
+        let config: Config = toml::from_str(text).expect("a valid config file");
+        config.layout.arms.iter().map(|a| a.angle_offset).collect()
+    }
+
+    #[test]
+    fn replaces_an_existing_angle_offset() {
+        let text = "\
+[[arm]]
+leds = 23
+wire_order = 0
+direction = \"outwards\"
+angle = 0.5
+angle_offset = 0.1
+";
+        let updated = set_angle_offsets(text, &[0.025]).unwrap();
+        assert_eq!(
+            updated,
+            "\
+[[arm]]
+angle_offset = 0.0250
+leds = 23
+wire_order = 0
+direction = \"outwards\"
+angle = 0.5
+"
+        );
+        assert_eq!(offsets_in(&updated), [0.025]);
+    }
+
+    #[test]
+    fn inserts_an_angle_offset_where_there_is_none() {
+        let text = "\
+chipset = \"apa102\"
+
+[[arm]]
+leds = 23
+wire_order = 0
+direction = \"outwards\"
+angle = 0.5
+
+[[arm]]
+leds = 23
+wire_order = 1
+direction = \"inwards\"
+angle = 0.0
+angle_offset = -0.2
+";
+        let updated = set_angle_offsets(text, &[0.0083, -0.0167]).unwrap();
+        assert_eq!(
+            updated,
+            "\
+chipset = \"apa102\"
+
+[[arm]]
+angle_offset = 0.0083
+leds = 23
+wire_order = 0
+direction = \"outwards\"
+angle = 0.5
+
+[[arm]]
+angle_offset = -0.0167
+leds = 23
+wire_order = 1
+direction = \"inwards\"
+angle = 0.0
+"
+        );
+        assert_eq!(offsets_in(&updated), [0.0083, -0.0167]);
+    }
+
+    #[test]
+    fn keeps_comments_on_arm_headers_and_offsets_elsewhere() {
+        let text = "\
+# the hub end is the first LED
+[[arm]] # left
+leds = 23
+wire_order = 0
+direction = \"outwards\"
+angle = 0.5
+angle_offset = 0.3 # from the last calibration
+
+[[ arm ]]   # right
+leds = 23
+wire_order = 1
+direction = \"inwards\"
+angle = 0.0
+
+[wheel]
+circumference_m = 2.1 # angle_offset = 1 is not an arm setting
+";
+        let updated = set_angle_offsets(text, &[0.1, 0.2]).unwrap();
+        assert_eq!(
+            updated,
+            "\
+# the hub end is the first LED
+[[arm]] # left
+angle_offset = 0.1000
+leds = 23
+wire_order = 0
+direction = \"outwards\"
+angle = 0.5
+
+[[ arm ]]   # right
+angle_offset = 0.2000
+leds = 23
+wire_order = 1
+direction = \"inwards\"
+angle = 0.0
+
+[wheel]
+circumference_m = 2.1 # angle_offset = 1 is not an arm setting
+"
+        );
+        assert_eq!(offsets_in(&updated), [0.1, 0.2]);
+    }
+
+    #[test]
+    fn leaves_an_angle_offset_after_the_arms_alone() {
+        // not a valid config file, but only lines in [[arm]] tables are
+        // the arms' to change
+        let text = "\
+[[arm]]
+leds = 23
+
+[other]
+angle_offset = 0.5
+";
+        let updated = set_angle_offsets(text, &[0.25]).unwrap();
+        assert_eq!(
+            updated,
+            "\
+[[arm]]
+angle_offset = 0.2500
+leds = 23
+
+[other]
+angle_offset = 0.5
+"
+        );
+    }
+
+    #[test]
+    fn multi_line_arrays_in_an_arm_are_not_table_headers() {
+        let text = "\
+[[arm]]
+leds = 23
+wire_order = 0
+direction = \"outwards\"
+angle = 0.5
+dead_leds = [
+    4,
+    7,
+]
+led_brightness = [
+    { led = 2, scale = 0.8 },
+    { led = 9, scale = 0.9 },
+]
+angle_offset = 0.3
+";
+        let updated = set_angle_offsets(text, &[-0.05]).unwrap();
+        assert_eq!(
+            updated,
+            "\
+[[arm]]
+angle_offset = -0.0500
+leds = 23
+wire_order = 0
+direction = \"outwards\"
+angle = 0.5
+dead_leds = [
+    4,
+    7,
+]
+led_brightness = [
+    { led = 2, scale = 0.8 },
+    { led = 9, scale = 0.9 },
+]
+"
+        );
+        assert_eq!(offsets_in(&updated), [-0.05]);
+    }
+
+    #[test]
+    fn mismatched_arm_count_is_an_error() {
+        let text = "\
+[[arm]]
+leds = 23
+";
+        let e = set_angle_offsets(text, &[0.0, 0.5]).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+
+        let e = set_angle_offsets(&text.repeat(3), &[0.0, 0.5]).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    }
+}
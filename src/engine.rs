@@ -0,0 +1,525 @@
+use std::cell::RefCell;
+use std::cmp;
+use std::io;
+use std::time::{Duration, Instant};
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::buttons::Button;
+use crate::calibrate::Calibrator;
+use crate::chill_modes::render_chill_mode;
+use crate::clock::Clock;
+use crate::jumble::Jumbler;
+use crate::leds::WheelLEDs;
+use crate::magnet::{MagnetLayout, RotationSensor};
+use crate::mode_test_pattern::construct_channel_test;
+use crate::moving_modes::modes;
+use crate::rotation::{RotationEstimator, WheelLimits};
+use crate::stopped_modes::{render_caution_mode, render_floodlight_mode};
+use crate::structs::{FrameState, Mode};
+use crate::timing::{FrameSchedule, FrameScheduler, LeadTime};
+
+/// The duration between magnet pulses that distinguishes between
+/// stopped mode and live mode.
+const STOP_TIME_MS: u128 = 2000;
+
+/// The duration between mode changes.
+const MODE_CHANGE_SEC: u64 = 20;
+
+/// A mode which runs all the time, even when the wheel is stopped, instead
+/// of the usual rotation of modes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ForcedMode {
+    /// an index into the list of moving modes
+    Index(usize),
+    /// the channel order test pattern
+    TestPattern,
+    /// the line for setting each arm's angle_offset with the buttons
+    Calibrate,
+}
+
+#[derive(PartialEq)]
+enum StoppedMode {
+    StoppedCaution,
+    StoppedWhite,
+    StoppedChill,
+}
+
+impl StoppedMode {
+    fn next(&self) -> StoppedMode {
+        match self {
+            StoppedMode::StoppedCaution => StoppedMode::StoppedWhite,
+            StoppedMode::StoppedWhite => StoppedMode::StoppedChill,
+            StoppedMode::StoppedChill => StoppedMode::StoppedCaution,
+        }
+    }
+}
+
+/// EngineInputs are what the Engine reads the wheel and the rider from:
+/// the real hardware on the bike, or stand-ins for it elsewhere.
+pub struct EngineInputs {
+    pub magnet: Box<dyn RotationSensor>,
+
+    /// the GPIO12 button, which cycles the stopped modes
+    pub push_button: Box<dyn Button>,
+
+    /// the GPIO13 button, which is only used by the calibrate mode
+    pub second_button: Box<dyn Button>,
+
+    pub clock: Box<dyn Clock>,
+}
+
+/// Statistics collected between mode changes
+#[derive(Default)]
+struct Statistics {
+    frames: u32,
+    peak_ma: f32,
+    limited_frames: u32,
+    peak_latency: Duration,
+}
+
+/// The Engine decides what the wheel shows: a stopped mode while it is
+/// stopped, cycled by the push button, and otherwise a moving mode, changed
+/// every MODE_CHANGE_SEC. Each call to frame() renders and shows one
+/// frame, so the same behaviour can be run on the bike, in the terminal
+/// preview, or under test.
+pub struct Engine<const LEDS: usize> {
+    inputs: EngineInputs,
+    wheel_leds: WheelLEDs<LEDS>,
+    forced_mode: Option<ForcedMode>,
+
+    rotation: RotationEstimator,
+    circumference_m: f32,
+    lead_time: LeadTime,
+    scheduler: FrameScheduler,
+
+    start_time: Instant,
+    spin_start_time: Instant,
+    loop_counter: u32,
+
+    /// whether the previous frame was for a stopped wheel, which decides
+    /// how long to wait before the next
+    stopped: bool,
+
+    floodlight: StoppedMode,
+
+    /// where each mode's seed comes from, after the first
+    seeds: StdRng,
+    /// the seed for the next mode, until the first has been constructed
+    first_seed: Option<u64>,
+
+    jumbler: Jumbler<usize>,
+    /// the moving mode, which is not chosen until the wheel first moves
+    /// unless it is forced
+    mode: Option<Box<dyn Mode<LEDS>>>,
+    /// the mode's random numbers, lent to it in each frame's FrameState
+    mode_rng: StdRng,
+    next_mode_time: Instant,
+
+    calibrator: Option<Calibrator>,
+
+    stats: Statistics,
+    stats_start_time: Instant,
+}
+
+impl<const LEDS: usize> Engine<LEDS> {
+    /// Makes an Engine and blanks the LEDs. Frames are scheduled as set by
+    /// FrameSchedule::from_env. The first mode is seeded with seed, or a
+    /// random seed if it is None, and the seeds of the modes after it, and
+    /// the order they come in, follow from it. Seeds are logged as modes
+    /// start, so that any of them can be given again to replay that mode.
+    pub fn new(
+        inputs: EngineInputs,
+        mut wheel_leds: WheelLEDs<LEDS>,
+        magnets: MagnetLayout,
+        limits: WheelLimits,
+        forced_mode: Option<ForcedMode>,
+        seed: Option<u64>,
+    ) -> io::Result<Engine<LEDS>> {
+        let start_time = inputs.clock.now();
+
+        for side in wheel_leds.sides() {
+            for led in 0..LEDS {
+                wheel_leds.set(side, led, (0, 0, 0));
+            }
+        }
+        wheel_leds.show(inputs.clock.as_ref())?;
+
+        let first_seed = seed.unwrap_or_else(|| rand::thread_rng().gen());
+        let mut seeds = StdRng::seed_from_u64(first_seed);
+
+        let jumbler = Jumbler::new((0..modes::<LEDS>().len()).collect(), seeds.gen());
+
+        let mut mode_rng = StdRng::seed_from_u64(first_seed);
+        let mode = match forced_mode {
+            Some(ForcedMode::Index(m)) => Some(construct_mode(m, first_seed, &mut mode_rng)),
+            Some(ForcedMode::TestPattern) => Some(construct_channel_test()),
+            None | Some(ForcedMode::Calibrate) => None,
+        };
+
+        let calibrator = match forced_mode {
+            Some(ForcedMode::Calibrate) => Some(Calibrator::new()),
+            _ => None,
+        };
+
+        Ok(Engine {
+            inputs,
+            wheel_leds,
+            forced_mode,
+            rotation: RotationEstimator::new(magnets, limits),
+            circumference_m: limits.circumference_m,
+            lead_time: LeadTime::new(),
+            scheduler: FrameScheduler::new(FrameSchedule::from_env()),
+            start_time,
+            spin_start_time: start_time,
+            loop_counter: 0,
+            stopped: true,
+            floodlight: StoppedMode::StoppedCaution,
+            seeds,
+            first_seed: Some(first_seed),
+            jumbler,
+            mode,
+            mode_rng,
+            next_mode_time: start_time,
+            calibrator,
+            stats: Statistics::default(),
+            stats_start_time: start_time,
+        })
+    }
+
+    /// Changes how frames are scheduled from the next frame on
+    pub fn set_schedule(&mut self, schedule: FrameSchedule) {
+        self.scheduler = FrameScheduler::new(schedule);
+    }
+
+    pub fn wheel_leds(&self) -> &WheelLEDs<LEDS> {
+        &self.wheel_leds
+    }
+
+    pub fn wheel_leds_mut(&mut self) -> &mut WheelLEDs<LEDS> {
+        &mut self.wheel_leds
+    }
+
+    /// Waits until the next frame is due, then reads the inputs, and
+    /// renders and shows the frame
+    pub fn frame(&mut self) -> io::Result<()> {
+        if self.stopped {
+            self.scheduler.wait_stopped(self.inputs.clock.as_ref());
+        } else {
+            let lead = self.lead_time.predict(self.wheel_leds.latency());
+            self.scheduler
+                .wait_moving(self.inputs.clock.as_ref(), &self.rotation, lead);
+        }
+
+        while let Some(pulse_time) = self.inputs.magnet.next_pulse() {
+            if self.rotation.pulse(pulse_time) {
+                self.spin_start_time = pulse_time;
+            }
+        }
+
+        let pressed = self.inputs.push_button.pulsed();
+        let second_pressed = self.inputs.second_button.pulsed();
+        if let Some(calibrator) = self.calibrator.as_mut() {
+            if pressed {
+                calibrator.nudge(&mut self.wheel_leds, 1);
+            }
+            // the line can only be seen while the wheel turns, so the
+            // second button moves it back then, and changes arm otherwise
+            if second_pressed && self.stopped {
+                calibrator.next_arm(&self.wheel_leds);
+            } else if second_pressed {
+                calibrator.nudge(&mut self.wheel_leds, -1);
+            }
+        } else if pressed {
+            println!("push button pulse");
+            self.floodlight = self.floodlight.next();
+        }
+
+        let spin_length = self.rotation.last_revolution();
+
+        // the wheel will have moved on by the time this frame lights up
+        let frame_start = self.inputs.clock.now();
+        let lit = frame_start + self.lead_time.predict(self.wheel_leds.latency());
+        let estimate = self.rotation.estimate(lit);
+
+        let mode_duration = cmp::max(
+            frame_start.saturating_duration_since(self.spin_start_time),
+            spin_length,
+        );
+
+        let framestate = FrameState {
+            now: frame_start - self.start_time,
+            loop_counter: self.loop_counter,
+            spin_pos: estimate.angle,
+            arm_angles: self.wheel_leds.layout().arm_angles(estimate.angle),
+            angular_velocity: estimate.angular_velocity,
+            confidence: estimate.confidence,
+            spin_length,
+            latency: self.wheel_leds.latency(),
+            circumference_m: self.circumference_m,
+            rng: RefCell::new(self.mode_rng.clone()),
+        };
+
+        // until the wheel has turned once since it was last stopped, a
+        // pulse may only be a knock to the parked bike
+        self.stopped = spin_length.is_zero() || mode_duration.as_millis() > STOP_TIME_MS;
+
+        if self.stopped && self.forced_mode.is_none() {
+            let wheel_leds = &mut self.wheel_leds;
+            match self.floodlight {
+                StoppedMode::StoppedCaution => render_caution_mode(wheel_leds, &framestate),
+                StoppedMode::StoppedWhite => render_floodlight_mode(wheel_leds, &framestate),
+                StoppedMode::StoppedChill => render_chill_mode(wheel_leds, &framestate),
+            }?;
+        } else {
+            if self.next_mode_time <= frame_start && self.forced_mode.is_none() {
+                let seed = self.first_seed.take().unwrap_or_else(|| self.seeds.gen());
+                let index = self.jumbler.next().unwrap();
+                self.mode = Some(construct_mode(index, seed, &mut framestate.rng()));
+                self.next_mode_time = frame_start + Duration::from_secs(MODE_CHANGE_SEC);
+
+                self.print_statistics(frame_start);
+                self.lead_time.reset();
+            }
+
+            if let Some(calibrator) = self.calibrator.as_ref() {
+                calibrator.render(&mut self.wheel_leds, &framestate)?;
+            } else if let Some(mode) = self.mode.as_mut() {
+                mode.pre_step(&framestate)?;
+                for side in self.wheel_leds.sides() {
+                    mode.render(side, &mut self.wheel_leds, &framestate)?;
+                }
+                mode.step(&framestate)?;
+            }
+        }
+        self.mode_rng = framestate.rng.into_inner();
+
+        self.wheel_leds.show(self.inputs.clock.as_ref())?;
+        self.lead_time.rendered(
+            self.inputs
+                .clock
+                .now()
+                .saturating_duration_since(frame_start),
+        );
+
+        let power = self.wheel_leds.power();
+        self.stats.peak_ma = self.stats.peak_ma.max(power.estimated_ma);
+        if power.limited() {
+            self.stats.limited_frames += 1;
+        }
+        self.stats.peak_latency = cmp::max(self.stats.peak_latency, self.wheel_leds.latency());
+
+        self.loop_counter += 1;
+        self.stats.frames += 1;
+
+        Ok(())
+    }
+
+    /// Prints the statistics collected since the previous call, and starts
+    /// collecting them again
+    fn print_statistics(&mut self, now: Instant) {
+        let stats_duration = now.saturating_duration_since(self.stats_start_time);
+        let stats_fps = (self.stats.frames as f32) / (stats_duration.as_secs() as f32);
+        println!(
+            "Frame rate statistics: {} frames over {:?} = {} frames/s",
+            self.stats.frames, stats_duration, stats_fps
+        );
+        println!(
+            "Current statistics: peak estimate {:.0} mA, {} frames dimmed to fit budget",
+            self.stats.peak_ma, self.stats.limited_frames
+        );
+        println!(
+            "Latency statistics: peak {:?} from show to LEDs, {:?} to render",
+            self.stats.peak_latency,
+            self.lead_time.render_time()
+        );
+        let pulses = self.rotation.pulse_counts();
+        println!(
+            "Magnet statistics: {} pulses used, {} rejected as glitches, {} missed",
+            pulses.accepted, pulses.rejected, pulses.missed
+        );
+
+        self.stats = Statistics::default();
+        self.stats_start_time = now;
+    }
+
+    /// Runs a shutdown effect, leaving the LEDs dark
+    pub fn shut_down(&mut self) -> io::Result<()> {
+        let duration_secs = (self.inputs.clock.now() - self.start_time).as_secs();
+        println!("Duration {} seconds", duration_secs);
+
+        for side in self.wheel_leds.sides() {
+            for led in 0..LEDS {
+                self.wheel_leds.set_linear(side, led, (1, 1, 1));
+            }
+        }
+        self.wheel_leds.show(self.inputs.clock.as_ref())?;
+
+        let clock = &self.inputs.clock;
+        clock.sleep_until(clock.now() + Duration::from_millis(250));
+
+        for side in self.wheel_leds.sides() {
+            for led in 0..LEDS {
+                self.wheel_leds.set(side, led, (0, 0, 0));
+            }
+        }
+        self.wheel_leds.show(self.inputs.clock.as_ref())?;
+
+        Ok(())
+    }
+}
+
+/// Constructs the moving mode with the given index, with its random
+/// numbers, in rng, started again from seed
+fn construct_mode<const LEDS: usize>(
+    index: usize,
+    seed: u64,
+    rng: &mut StdRng,
+) -> Box<dyn Mode<LEDS>> {
+    println!("Starting mode {index} with seed {seed}");
+    *rng = StdRng::seed_from_u64(seed);
+    (modes()[index])(rng)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::mpsc::{channel, Receiver, Sender};
+
+    use crate::clock::ManualClock;
+    use crate::layout::Layout;
+    use crate::leds::CaptureSink;
+
+    /// A magnet which pulses whenever the test sends it a time
+    struct ScriptedMagnet {
+        pulses: Receiver<Instant>,
+    }
+
+    impl RotationSensor for ScriptedMagnet {
+        fn next_pulse(&mut self) -> Option<Instant> {
+            self.pulses.try_recv().ok()
+        }
+    }
+
+    /// A button which is never pressed
+    struct NoButton;
+
+    impl Button for NoButton {
+        fn pulsed(&mut self) -> bool {
+            false
+        }
+    }
+
+    /// An Engine on a ManualClock, with the clock and a way to pulse its
+    /// magnet
+    fn test_engine() -> (Engine<23>, ManualClock, Sender<Instant>) {
+        let clock = ManualClock::new(Instant::now());
+        let (pulse, pulses) = channel();
+
+        let inputs = EngineInputs {
+            magnet: Box::new(ScriptedMagnet { pulses }),
+            push_button: Box::new(NoButton),
+            second_button: Box::new(NoButton),
+            clock: Box::new(clock.clone()),
+        };
+        let wheel_leds = WheelLEDs::with_sink(Layout::default(), Box::new(CaptureSink::new()));
+
+        let mut engine = Engine::new(
+            inputs,
+            wheel_leds,
+            MagnetLayout::default(),
+            WheelLimits::default(),
+            None,
+            Some(1),
+        )
+        .unwrap();
+        engine.set_schedule(FrameSchedule::default());
+
+        (engine, clock, pulse)
+    }
+
+    /// Moves the clock on by after, pulses the magnet then and renders a
+    /// frame
+    fn pulse_and_frame(
+        engine: &mut Engine<23>,
+        clock: &ManualClock,
+        pulse: &Sender<Instant>,
+        after: Duration,
+    ) {
+        clock.advance(after);
+        pulse.send(clock.now()).unwrap();
+        engine.frame().unwrap();
+    }
+
+    #[test]
+    fn moving_mode_changes_every_mode_change_sec() {
+        let (mut engine, clock, pulse) = test_engine();
+        let start = clock.now();
+
+        // a frame every 50ms on a wheel turning twice a second
+        let revolution = Duration::from_millis(500);
+        let mut next_pulse = start + revolution;
+        let mut next_mode_time = engine.next_mode_time;
+        let mut changes = Vec::new();
+
+        while clock.now() < start + Duration::from_secs(2 * MODE_CHANGE_SEC + 5) {
+            clock.advance(Duration::from_millis(50));
+            if next_pulse <= clock.now() {
+                pulse.send(next_pulse).unwrap();
+                next_pulse += revolution;
+            }
+            engine.frame().unwrap();
+
+            if engine.next_mode_time != next_mode_time {
+                next_mode_time = engine.next_mode_time;
+                changes.push(clock.now() - start);
+            }
+        }
+
+        // the first mode starts once the wheel has turned once
+        assert_eq!(
+            changes,
+            [1, 1 + MODE_CHANGE_SEC, 1 + 2 * MODE_CHANGE_SEC].map(Duration::from_secs)
+        );
+
+        // frames take no time on a ManualClock
+        assert_eq!(engine.wheel_leds().latency(), Duration::ZERO);
+        assert_eq!(engine.lead_time.render_time(), Duration::ZERO);
+    }
+
+    #[test]
+    fn first_pulse_after_starting_leaves_the_wheel_stopped() {
+        let (mut engine, clock, pulse) = test_engine();
+        engine.frame().unwrap();
+
+        pulse_and_frame(&mut engine, &clock, &pulse, Duration::from_secs(30));
+        assert!(engine.stopped);
+        assert!(engine.mode.is_none());
+
+        pulse_and_frame(&mut engine, &clock, &pulse, Duration::from_millis(500));
+        assert!(!engine.stopped);
+        assert!(engine.mode.is_some());
+    }
+
+    #[test]
+    fn one_pulse_after_a_long_gap_leaves_the_wheel_stopped() {
+        let (mut engine, clock, pulse) = test_engine();
+        for _ in 0..4 {
+            pulse_and_frame(&mut engine, &clock, &pulse, Duration::from_millis(500));
+        }
+        assert!(!engine.stopped);
+        let next_mode_time = engine.next_mode_time;
+
+        // parked for long enough that the rotation history is forgotten,
+        // and then knocked
+        clock.advance(Duration::from_secs(60));
+        engine.frame().unwrap();
+        assert!(engine.stopped);
+
+        pulse_and_frame(&mut engine, &clock, &pulse, Duration::from_secs(1));
+        assert!(engine.stopped);
+        assert_eq!(engine.next_mode_time, next_mode_time);
+    }
+}
@@ -0,0 +1,119 @@
+use std::env;
+use std::io;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::time::{Duration, Instant};
+
+use gpiocdev::line::{Bias, EdgeDetection};
+use gpiocdev::Request;
+
+/// The GPIO character device used when RUSTY_WHEELS_GPIOCHIP is not set
+const DEFAULT_GPIOCHIP: &str = "/dev/gpiochip0";
+
+/// An EdgeLine is a GPIO input which reports rising edges. GpioLine is the
+/// real one; FakeLine lets edges be injected without hardware.
+pub trait EdgeLine: Send {
+    /// Waits up to timeout for the next rising edge and returns the time
+    /// it happened, or None if there was no edge in time. A timeout of
+    /// None waits for ever, and a timeout of zero only checks for edges
+    /// which have already happened.
+    fn wait_edge(&mut self, timeout: Option<Duration>) -> io::Result<Option<Instant>>;
+}
+
+/// GpioLine is one line of the GPIO character device, requested as an
+/// input with its pull-up turned on and rising edges detected. Edges are
+/// queued and timestamped by the kernel as the interrupt happens, so the
+/// time of an edge does not depend on how soon it is read.
+pub struct GpioLine {
+    request: Request,
+}
+
+impl GpioLine {
+    /// Requests line offset (the BCM GPIO number on a Raspberry Pi) from
+    /// /dev/gpiochip0, or the chip named by RUSTY_WHEELS_GPIOCHIP.
+    /// consumer is shown against the line by tools like gpioinfo.
+    pub fn new(offset: u32, consumer: &str) -> io::Result<GpioLine> {
+        let chip = match env::var("RUSTY_WHEELS_GPIOCHIP") {
+            Ok(c) => c,
+            Err(_) => DEFAULT_GPIOCHIP.to_string(),
+        };
+
+        let request = Request::builder()
+            .on_chip(chip)
+            .with_consumer(consumer)
+            .with_line(offset)
+            .as_input()
+            .with_bias(Bias::PullUp)
+            .with_edge_detection(EdgeDetection::RisingEdge)
+            .request()
+            .map_err(io::Error::other)?;
+
+        Ok(GpioLine { request })
+    }
+}
+
+impl EdgeLine for GpioLine {
+    fn wait_edge(&mut self, timeout: Option<Duration>) -> io::Result<Option<Instant>> {
+        if let Some(timeout) = timeout {
+            let ready = self
+                .request
+                .wait_edge_event(timeout)
+                .map_err(io::Error::other)?;
+            if !ready {
+                return Ok(None);
+            }
+        }
+
+        let event = self.request.read_edge_event().map_err(io::Error::other)?;
+        Ok(Some(monotonic_to_instant(event.timestamp_ns)))
+    }
+}
+
+/// Converts a CLOCK_MONOTONIC timestamp from the kernel into an Instant.
+/// Instant uses the same clock on Linux but cannot be made from a
+/// timestamp, so this works back from the current time on both.
+fn monotonic_to_instant(timestamp_ns: u64) -> Instant {
+    let now = Instant::now();
+
+    let mut ts = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    // SAFETY: ts is a valid timespec for clock_gettime to write to
+    unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut ts) };
+    let now_ns = ts.tv_sec as u64 * 1_000_000_000 + ts.tv_nsec as u64;
+
+    let age = Duration::from_nanos(now_ns.saturating_sub(timestamp_ns));
+    now.checked_sub(age).unwrap_or(now)
+}
+
+/// FakeLine is an EdgeLine which reports edges sent to it through the
+/// Sender returned alongside it. Once the Sender is dropped and all sent
+/// edges have been read, waiting returns an error, like a line which has
+/// gone away.
+pub struct FakeLine {
+    edges: Receiver<Instant>,
+}
+
+impl FakeLine {
+    pub fn new() -> (FakeLine, Sender<Instant>) {
+        let (sender, edges) = mpsc::channel();
+        (FakeLine { edges }, sender)
+    }
+}
+
+fn line_closed() -> io::Error {
+    io::Error::new(io::ErrorKind::BrokenPipe, "fake line closed")
+}
+
+impl EdgeLine for FakeLine {
+    fn wait_edge(&mut self, timeout: Option<Duration>) -> io::Result<Option<Instant>> {
+        match timeout {
+            None => self.edges.recv().map(Some).map_err(|_| line_closed()),
+            Some(t) => match self.edges.recv_timeout(t) {
+                Ok(edge) => Ok(Some(edge)),
+                Err(RecvTimeoutError::Timeout) => Ok(None),
+                Err(RecvTimeoutError::Disconnected) => Err(line_closed()),
+            },
+        }
+    }
+}
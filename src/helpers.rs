@@ -2,8 +2,9 @@ use crate::structs::FrameState;
 use palette::encoding::pixel::Pixel;
 use palette::Hsv;
 use palette::Srgb;
+use std::ops::Range;
 
-use crate::structs::RGB24;
+use crate::structs::{RGB24, RGB48};
 
 /// Turns spin position into a saturated rainbow wheel
 pub fn spinpos_to_rgb(framestate: &FrameState) -> RGB24 {
@@ -12,6 +13,23 @@ pub fn spinpos_to_rgb(framestate: &FrameState) -> RGB24 {
 
 /// turns a value from 0..1 into RGB
 pub fn fraction_to_rgb(fraction: f32, value: Option<f32>) -> RGB24 {
+    let pixels: [u8; 3] = fraction_to_srgb(fraction, value).into_format().into_raw();
+
+    let [red, green, blue] = pixels;
+
+    (red, green, blue)
+}
+
+/// turns a value from 0..1 into RGB with 16 bits per channel
+pub fn fraction_to_rgb48(fraction: f32, value: Option<f32>) -> RGB48 {
+    let pixels: [u16; 3] = fraction_to_srgb(fraction, value).into_format().into_raw();
+
+    let [red, green, blue] = pixels;
+
+    (red, green, blue)
+}
+
+fn fraction_to_srgb(fraction: f32, value: Option<f32>) -> Srgb {
     let hue = (fraction * 360.0).min(360.0);
 
     let real_value = match value {
@@ -21,13 +39,7 @@ pub fn fraction_to_rgb(fraction: f32, value: Option<f32>) -> RGB24 {
 
     let hsv: Hsv = Hsv::from_components((hue, 1.0, real_value));
 
-    let srgb = Srgb::from(hsv);
-
-    let pixels: [u8; 3] = srgb.into_linear().into_format().into_raw();
-
-    let [red, green, blue] = pixels;
-
-    (red, green, blue)
+    Srgb::from(hsv)
 }
 
 pub fn blank_leds(wheel_leds: &mut [RGB24]) {
@@ -35,3 +47,28 @@ pub fn blank_leds(wheel_leds: &mut [RGB24]) {
         *l = (0, 0, 0);
     }
 }
+
+/// Sets the LEDs inwards from the rim to colours, rim first. Colours which
+/// would fall off the hub end of a short arm are left out.
+pub fn set_from_rim(wheel_leds: &mut [RGB24], colours: &[RGB24]) {
+    for (led, colour) in wheel_leds.iter_mut().rev().zip(colours) {
+        *led = *colour;
+    }
+}
+
+/// The LED nearest to a fraction of the radius, from 0.0 at the hub to
+/// 1.0 at the rim, on an arm of leds LEDs. This lets modes place things
+/// without depending on how many LEDs there are.
+pub fn led_at(fraction: f32, leds: usize) -> usize {
+    led_edge(fraction, leds).min(leds - 1)
+}
+
+/// The LEDs from fraction from of the radius up to fraction to, where
+/// 0.0 is the hub and 1.0 is the rim, on an arm of leds LEDs.
+pub fn leds_between(from: f32, to: f32, leds: usize) -> Range<usize> {
+    led_edge(from, leds)..led_edge(to, leds)
+}
+
+fn led_edge(fraction: f32, leds: usize) -> usize {
+    ((fraction * leds as f32).round().max(0.0) as usize).min(leds)
+}
@@ -1,14 +1,18 @@
+use rand::rngs::StdRng;
 use rand::seq::SliceRandom;
-use rand::Rng;
+use rand::{Rng, SeedableRng};
 
 pub struct Jumbler<T> {
     content: Vec<T>,
+    rng: StdRng,
 }
 
 impl<T> Jumbler<T> {
-    pub fn new(mut content: Vec<T>) -> Jumbler<T> {
-        content.shuffle(&mut rand::thread_rng());
-        Jumbler { content }
+    /// A Jumbler whose order is decided by seed, so that it can be replayed
+    pub fn new(mut content: Vec<T>, seed: u64) -> Jumbler<T> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        content.shuffle(&mut rng);
+        Jumbler { content, rng }
     }
 }
 
@@ -19,7 +23,7 @@ impl<T: Copy> Iterator for Jumbler<T> {
         let next_index = if self.content.len() == 1 {
             0
         } else {
-            rand::thread_rng().gen_range(0, self.content.len() / 2)
+            self.rng.gen_range(0, self.content.len() / 2)
         };
 
         let entry = self.content.remove(next_index);
@@ -0,0 +1,307 @@
+use std::io;
+
+use serde::Deserialize;
+
+use crate::leds::Side;
+use crate::structs::RGB48;
+
+/// The direction that the LED chain runs along an arm
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChainDirection {
+    /// the first LED on the arm is nearest the hub
+    Outwards,
+    /// the first LED on the arm is nearest the rim
+    Inwards,
+}
+
+/// ChannelOrder describes a strip which does not put colours where its
+/// chipset should, written as the colours it shows when sent red, green
+/// and blue. "rgb" is a strip which is correct; a strip which shows red,
+/// blue, green is "rbg".
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct ChannelOrder([usize; 3]);
+
+impl Default for ChannelOrder {
+    fn default() -> ChannelOrder {
+        ChannelOrder([0, 1, 2])
+    }
+}
+
+impl TryFrom<String> for ChannelOrder {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<ChannelOrder, String> {
+        let mut order = [0; 3];
+        let mut seen = [false; 3];
+        if s.len() != 3 {
+            return Err(format!("channel order {s:?} needs three letters"));
+        }
+        for (n, c) in s.chars().enumerate() {
+            let channel = match c {
+                'r' => 0,
+                'g' => 1,
+                'b' => 2,
+                _ => return Err(format!("channel order {s:?} can only contain r, g and b")),
+            };
+            if seen[channel] {
+                return Err(format!("channel order {s:?} repeats {c}"));
+            }
+            seen[channel] = true;
+            order[n] = channel;
+        }
+        Ok(ChannelOrder(order))
+    }
+}
+
+impl ChannelOrder {
+    /// Rearranges a colour so that the strip shows it as intended
+    pub fn apply(&self, rgb: RGB48) -> RGB48 {
+        let (r, g, b) = rgb;
+        let channels = [r, g, b];
+        let [o0, o1, o2] = self.0;
+        (channels[o0], channels[o1], channels[o2])
+    }
+}
+
+/// What to show in place of a dead LED
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeadFill {
+    /// leave the dead LED dark and spread the arm's pattern across the
+    /// remaining LEDs, so that no ring of the pattern goes missing
+    #[default]
+    Skip,
+    /// send the dead LED the same colour as its nearest working
+    /// neighbour, for LEDs which still light but wrongly
+    Mirror,
+}
+
+/// A brightness correction for one LED, such as one which has aged and
+/// dimmed
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LedBrightness {
+    /// the LED on the arm, counted from 0 at the hub
+    pub led: usize,
+
+    /// scale applied to this LED's light output
+    pub scale: f32,
+}
+
+/// ArmLayout describes one arm of LEDs radiating from the hub
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ArmLayout {
+    /// the number of physical LEDs on the arm
+    pub leds: usize,
+
+    /// where the arm comes along the daisy chain: arms are sent in
+    /// increasing wire order
+    pub wire_order: usize,
+
+    /// which way the chain runs along the arm
+    pub direction: ChainDirection,
+
+    /// where the arm sits relative to angle 0 (the first magnet, usually),
+    /// in revolutions in the direction of rotation
+    pub angle: f32,
+
+    /// a correction to angle, in revolutions, so that angle 0 is straight
+    /// up. This is set by the calibrate mode.
+    #[serde(default)]
+    pub angle_offset: f32,
+
+    /// true if the LEDs on this arm face the other side of the bike, so
+    /// that they are seen turning the other way
+    #[serde(default)]
+    pub mirrored: bool,
+
+    /// the colour order of this arm's LEDs, for strips which do not
+    /// follow their chipset
+    #[serde(default)]
+    pub channel_order: ChannelOrder,
+
+    /// LEDs on this arm, counted from 0 at the hub, which have failed
+    #[serde(default)]
+    pub dead_leds: Vec<usize>,
+
+    /// what to do about dead LEDs
+    #[serde(default)]
+    pub dead_fill: DeadFill,
+
+    /// brightness corrections for individual LEDs on this arm
+    #[serde(default)]
+    pub led_brightness: Vec<LedBrightness>,
+}
+
+/// A Layout describes every arm on the wheel, in the order of their Sides:
+/// the first arm is Side::LEFT and the second Side::RIGHT.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(transparent)]
+pub struct Layout {
+    pub arms: Vec<ArmLayout>,
+}
+
+impl Default for Layout {
+    /// The original wheel: two arms of 23 LEDs on one chain, half a turn
+    /// apart and facing opposite sides of the bike. The chain runs out
+    /// along the left arm and then back in along the right arm.
+    fn default() -> Layout {
+        Layout {
+            arms: vec![
+                ArmLayout {
+                    leds: 23,
+                    wire_order: 0,
+                    direction: ChainDirection::Outwards,
+                    angle: 0.5,
+                    angle_offset: 0.0,
+                    mirrored: true,
+                    channel_order: ChannelOrder::default(),
+                    dead_leds: Vec::new(),
+                    dead_fill: DeadFill::default(),
+                    led_brightness: Vec::new(),
+                },
+                ArmLayout {
+                    leds: 23,
+                    wire_order: 1,
+                    direction: ChainDirection::Inwards,
+                    angle: 0.0,
+                    angle_offset: 0.0,
+                    mirrored: false,
+                    channel_order: ChannelOrder::default(),
+                    dead_leds: Vec::new(),
+                    dead_fill: DeadFill::default(),
+                    led_brightness: Vec::new(),
+                },
+            ],
+        }
+    }
+}
+
+impl Layout {
+    /// Checks that the layout describes something that can be wired up
+    pub fn validate(&self) -> io::Result<()> {
+        let invalid = |msg: String| Err(io::Error::new(io::ErrorKind::InvalidData, msg));
+
+        if self.arms.is_empty() {
+            return invalid("layout has no arms".to_string());
+        }
+
+        let mut wire_orders: Vec<usize> = self.arms.iter().map(|a| a.wire_order).collect();
+        wire_orders.sort_unstable();
+        wire_orders.dedup();
+        if wire_orders.len() != self.arms.len() {
+            return invalid("each arm needs a different wire_order".to_string());
+        }
+
+        for (n, arm) in self.arms.iter().enumerate() {
+            if arm.leds == 0 {
+                return invalid(format!("arm {n} has no LEDs"));
+            }
+
+            if let Some(led) = arm.dead_leds.iter().find(|led| **led >= arm.leds) {
+                return invalid(format!("arm {n} has no dead LED {led}"));
+            }
+
+            if (0..arm.leds).all(|led| arm.dead_leds.contains(&led)) {
+                return invalid(format!("arm {n} has no working LEDs"));
+            }
+
+            for correction in arm.led_brightness.iter() {
+                if correction.led >= arm.leds {
+                    return invalid(format!("arm {n} has no LED {}", correction.led));
+                }
+                if correction.scale < 0.0 {
+                    return invalid(format!(
+                        "arm {n} LED {} has negative brightness",
+                        correction.led
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn arm(&self, side: Side) -> &ArmLayout {
+        &self.arms[side.0]
+    }
+
+    /// Where one arm is, in revolutions from straight up, when the wheel
+    /// is at spin_pos, ranging from 0 up to but not including 1
+    pub fn arm_angle(&self, side: Side, spin_pos: f32) -> f32 {
+        let arm = self.arm(side);
+        (spin_pos + arm.angle + arm.angle_offset).rem_euclid(1.0)
+    }
+
+    /// Where each arm is when the wheel is at spin_pos, as seen from the
+    /// face of the wheel it faces, as described by FrameState::angle
+    pub fn arm_angles(&self, spin_pos: f32) -> Vec<f32> {
+        (0..self.arms.len())
+            .map(|n| {
+                let angle = self.arm_angle(Side(n), spin_pos);
+                if self.arms[n].mirrored {
+                    (1.0 - angle).rem_euclid(1.0)
+                } else {
+                    angle
+                }
+            })
+            .collect()
+    }
+
+    /// The total number of LEDs along the chain
+    pub fn total_leds(&self) -> usize {
+        self.arms.iter().map(|a| a.leds).sum()
+    }
+
+    /// The largest number of LEDs on any arm
+    pub fn max_leds(&self) -> usize {
+        self.arms.iter().map(|a| a.leds).max().unwrap_or(0)
+    }
+
+    /// The arms in the order they come along the chain
+    pub fn wire_order(&self) -> Vec<Side> {
+        let mut sides: Vec<Side> = (0..self.arms.len()).map(Side).collect();
+        sides.sort_by_key(|s| self.arm(*s).wire_order);
+        sides
+    }
+
+    /// The position along the chain of each of the LEDs on one arm,
+    /// starting at the hub
+    pub fn chain_indices(&self, side: Side) -> Vec<usize> {
+        let start: usize = self
+            .wire_order()
+            .into_iter()
+            .take_while(|s| *s != side)
+            .map(|s| self.arm(s).leds)
+            .sum();
+
+        let arm = self.arm(side);
+        match arm.direction {
+            ChainDirection::Outwards => (start..start + arm.leds).collect(),
+            ChainDirection::Inwards => (start..start + arm.leds).rev().collect(),
+        }
+    }
+
+    /// The channel order for each LED along the chain
+    pub fn channel_orders(&self) -> Vec<ChannelOrder> {
+        self.wire_order()
+            .into_iter()
+            .flat_map(|s| {
+                let arm = self.arm(s);
+                std::iter::repeat_n(arm.channel_order, arm.leds)
+            })
+            .collect()
+    }
+
+    /// Picks out one arm's LEDs, starting at the hub, from a frame as it
+    /// was given to a sink.
+    pub fn frame_arm<T: Copy>(&self, frame: &[T], side: Side) -> Vec<T> {
+        self.chain_indices(side)
+            .into_iter()
+            .map(|i| frame[i])
+            .collect()
+    }
+}
@@ -3,25 +3,28 @@ extern crate spidev;
 use spidev::SpiModeFlags;
 use spidev::Spidev;
 use spidev::SpidevOptions;
+use spidev::SpidevTransfer;
 
+use std::cmp;
+use std::env;
 use std::io;
-use std::io::BufWriter;
-use std::io::Write;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 
-use crate::structs::RGB24;
+use serde::Deserialize;
 
-fn setup_leds() -> io::Result<BufWriter<Spidev>> {
-    println!("Configuring LEDs");
-    let spi = create_spi()?;
-    Ok(BufWriter::new(spi))
-}
+use crate::clock::Clock;
+use crate::layout::{ArmLayout, ChainDirection, ChannelOrder, DeadFill, Layout};
+use crate::structs::{RGB24, RGB48};
 
-fn create_spi() -> io::Result<Spidev> {
+fn create_spi(speed_hz: u32) -> io::Result<Spidev> {
     let mut spi = Spidev::open("/dev/spidev0.0")?;
 
     let options = SpidevOptions::new()
         .bits_per_word(8)
-        .max_speed_hz(8_000_000)
+        .max_speed_hz(speed_hz)
         .mode(SpiModeFlags::SPI_MODE_0)
         .build();
     spi.configure(&options)?;
@@ -29,105 +32,1111 @@ fn create_spi() -> io::Result<Spidev> {
     Ok(spi)
 }
 
-fn send_led(w: &mut BufWriter<Spidev>, m: u8, r: u8, g: u8, b: u8) -> io::Result<usize> {
-    w.write(&[m, b, g, r])
+/// Splits a 16-bit colour into the APA102 5-bit global brightness and
+/// 8-bit PWM values. The global brightness is chosen as low as possible
+/// while still fitting the brightest channel, so that dim colours keep
+/// most of the 8 bits of PWM precision rather than a handful.
+///
+/// The brightness byte has the top three bits set, as the protocol
+/// requires.
+fn apa102_led(rgb: RGB48) -> (u8, u8, u8, u8) {
+    let (r, g, b) = rgb;
+    let max = r.max(g).max(b) as u32;
+
+    // smallest brightness in 1..=31 with max * 31 / brightness <= 65535
+    let brightness = (max * 31).div_ceil(65535).max(1);
+
+    let pwm = |v: u16| -> u8 {
+        let scaled = (v as u32 * 31 * 255 + brightness * 65535 / 2) / (brightness * 65535);
+        scaled.min(255) as u8
+    };
+
+    (0b1110_0000 | brightness as u8, pwm(r), pwm(g), pwm(b))
+}
+
+/// A LedSink receives each finished frame from WheelLEDs::show, with
+/// LED values in the order they sit along the physical chain.
+pub trait LedSink {
+    fn write_frame(&mut self, frame: &[RGB48]) -> io::Result<()>;
+
+    /// When the most recent frame to be completely written was handed
+    /// over and when it finished. Sinks which write each frame before
+    /// write_frame returns do not need to provide this.
+    fn last_timing(&self) -> Option<WireTiming> {
+        None
+    }
 }
 
-fn send_rgb(w: &mut BufWriter<Spidev>, rgb: RGB24) -> io::Result<usize> {
-    let (r, g, b) = rgb;
-    send_led(w, 255, r, g, b)
+/// WireTiming records how long a frame took to get from WheelLEDs::show
+/// onto the LEDs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WireTiming {
+    /// when show() handed the frame to the sink
+    pub handed_over: Instant,
+
+    /// when the sink finished writing the frame
+    pub written: Instant,
+}
+
+impl WireTiming {
+    pub fn latency(&self) -> Duration {
+        self.written.saturating_duration_since(self.handed_over)
+    }
+}
+
+/// The kind of LEDs on the strip, which decides how frames are sent
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Chipset {
+    /// APA102/DotStar, with separate clock and data lines
+    #[default]
+    Apa102,
+    /// WS2812B/NeoPixel, driven from the SPI data line alone
+    Ws2812,
+    /// SK6812 RGBW, like WS2812 with an extra white LED
+    Sk6812,
+}
+
+impl Chipset {
+    /// The SPI clock to send frames with
+    fn spi_speed_hz(&self) -> u32 {
+        match self {
+            Chipset::Apa102 => 8_000_000,
+            Chipset::Ws2812 | Chipset::Sk6812 => WS2812_SPI_HZ,
+        }
+    }
+
+    /// A new encoder for this chipset's wire format
+    pub fn encoder(&self) -> Box<dyn FrameEncoder + Send> {
+        match self {
+            Chipset::Apa102 => Box::new(Apa102Frame::new()),
+            Chipset::Ws2812 => Box::new(Ws2812Frame::new(false)),
+            Chipset::Sk6812 => Box::new(Ws2812Frame::new(true)),
+        }
+    }
+}
+
+/// A FrameEncoder turns a frame of LED values, in chain order, into the
+/// bytes to send down the SPI bus. The buffer is kept between frames so
+/// that encoding does not allocate.
+pub trait FrameEncoder {
+    /// Replaces the encoded frame with the given LED values
+    fn encode(&mut self, frame: &[RGB48]);
+
+    fn as_bytes(&self) -> &[u8];
+}
+
+/// Apa102Frame holds a whole frame encoded in the APA102 wire format, ready
+/// to be sent to the strip in one go: a start frame of 32 zero bits, four
+/// bytes for each LED, and an end frame.
+#[derive(Default)]
+pub struct Apa102Frame {
+    bytes: Vec<u8>,
+}
+
+impl Apa102Frame {
+    pub fn new() -> Apa102Frame {
+        Apa102Frame { bytes: Vec::new() }
+    }
+}
+
+impl FrameEncoder for Apa102Frame {
+    fn encode(&mut self, frame: &[RGB48]) {
+        self.bytes.clear();
+
+        // start frame
+        self.bytes.extend_from_slice(&[0; 4]);
+
+        for rgb in frame.iter() {
+            let (m, r, g, b) = apa102_led(*rgb);
+            self.bytes.extend_from_slice(&[m, b, g, r]);
+        }
+
+        // each LED delays the data by half a clock, so the clock has to
+        // keep going for half a bit per LED after the last LED's data to
+        // push it all the way down the strip.
+        let end_bytes = cmp::max(4, frame.len().div_ceil(16));
+        self.bytes.resize(self.bytes.len() + end_bytes, 0);
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+/// The SPI clock for WS2812 strips: three SPI bits for each 1.25us WS2812
+/// bit, so that a 0 can be sent as 100 and a 1 as 110.
+const WS2812_SPI_HZ: u32 = 2_400_000;
+
+/// How long the data line is held low after a frame so that the strip
+/// latches it. The datasheet says 50us, but newer WS2812B parts need
+/// 280us.
+const WS2812_RESET_US: u32 = 300;
+
+/// Ws2812Frame holds a frame encoded for WS2812 (or, with white, SK6812
+/// RGBW) LEDs, which have a single data line timed by pulse width. Each
+/// data bit is spread over three SPI bits at WS2812_SPI_HZ, and the frame
+/// ends with a low reset gap.
+/// These chipsets have no global brightness, so colours are sent with 8
+/// bits per channel.
+pub struct Ws2812Frame {
+    bytes: Vec<u8>,
+
+    /// true to send a fourth, white, channel taken from the part of the
+    /// colour common to red, green and blue
+    white: bool,
+}
+
+impl Ws2812Frame {
+    pub fn new(white: bool) -> Ws2812Frame {
+        Ws2812Frame {
+            bytes: Vec::new(),
+            white,
+        }
+    }
+
+    /// Appends one 8-bit channel value as three SPI bytes, most
+    /// significant bit first
+    fn push_channel(&mut self, v: u8) {
+        let mut bits: u32 = 0;
+        for n in (0..8).rev() {
+            let symbol = if v & (1 << n) != 0 { 0b110 } else { 0b100 };
+            bits = (bits << 3) | symbol;
+        }
+        self.bytes
+            .extend_from_slice(&[(bits >> 16) as u8, (bits >> 8) as u8, bits as u8]);
+    }
+}
+
+impl FrameEncoder for Ws2812Frame {
+    fn encode(&mut self, frame: &[RGB48]) {
+        self.bytes.clear();
+
+        for rgb in frame.iter() {
+            let (mut r, mut g, mut b) = narrow(*rgb);
+
+            // the strip expects green first
+            if self.white {
+                let w = r.min(g).min(b);
+                r -= w;
+                g -= w;
+                b -= w;
+                for v in [g, r, b, w] {
+                    self.push_channel(v);
+                }
+            } else {
+                for v in [g, r, b] {
+                    self.push_channel(v);
+                }
+            }
+        }
+
+        let reset_bytes = (WS2812_SPI_HZ / 8 * WS2812_RESET_US).div_ceil(1_000_000);
+        self.bytes
+            .resize(self.bytes.len() + reset_bytes as usize, 0);
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+/// SpiSink drives an LED strip on /dev/spidev0.0, sending each frame as a
+/// single SPI transfer. Each LED's colour is rearranged for its arm's
+/// channel order first. The kernel limits the size of a transfer (4096
+/// bytes unless the spidev bufsiz parameter is raised), which is enough
+/// for about a thousand APA102 LEDs or a few hundred WS2812s.
+pub struct SpiSink {
+    spi: Spidev,
+    encoder: Box<dyn FrameEncoder + Send>,
+
+    /// channel_orders has an entry for each LED along the chain
+    channel_orders: Vec<ChannelOrder>,
+
+    /// reordered is reused on each frame to hold the rearranged colours
+    reordered: Vec<RGB48>,
+}
+
+impl SpiSink {
+    pub fn new(chipset: Chipset, channel_orders: Vec<ChannelOrder>) -> io::Result<SpiSink> {
+        println!("Configuring {chipset:?} LEDs");
+        let spi = create_spi(chipset.spi_speed_hz())?;
+        Ok(SpiSink {
+            spi,
+            encoder: chipset.encoder(),
+            reordered: Vec::with_capacity(channel_orders.len()),
+            channel_orders,
+        })
+    }
+}
+
+impl LedSink for SpiSink {
+    fn write_frame(&mut self, frame: &[RGB48]) -> io::Result<()> {
+        self.reordered.clear();
+        self.reordered.extend(
+            frame
+                .iter()
+                .zip(self.channel_orders.iter())
+                .map(|(rgb, order)| order.apply(*rgb)),
+        );
+
+        self.encoder.encode(&self.reordered);
+        let mut transfer = SpidevTransfer::write(self.encoder.as_bytes());
+        self.spi.transfer(&mut transfer)
+    }
+}
+
+/// ThreadedSink writes frames to another sink on a dedicated writer
+/// thread, so that the next frame can be rendered while the previous one
+/// is still being sent. write_frame only waits if the writer has not
+/// finished with the previous frame yet.
+/// Errors from the other sink are returned by the following call to
+/// write_frame.
+pub struct ThreadedSink {
+    to_writer: Option<Sender<(Vec<RGB48>, Instant)>>,
+    from_writer: Receiver<(Vec<RGB48>, io::Result<WireTiming>)>,
+
+    /// the buffer for the next frame, when the writer is not using it
+    buffer: Option<Vec<RGB48>>,
+
+    timing: Option<WireTiming>,
+
+    writer: Option<JoinHandle<()>>,
+}
+
+impl ThreadedSink {
+    pub fn new(mut sink: Box<dyn LedSink + Send>) -> ThreadedSink {
+        let (to_writer, frames) = mpsc::channel::<(Vec<RGB48>, Instant)>();
+        let (written, from_writer) = mpsc::channel();
+
+        let writer = thread::spawn(move || {
+            for (frame, handed_over) in frames {
+                let result = sink.write_frame(&frame).map(|()| WireTiming {
+                    handed_over,
+                    written: Instant::now(),
+                });
+                if written.send((frame, result)).is_err() {
+                    break;
+                }
+            }
+        });
+
+        ThreadedSink {
+            to_writer: Some(to_writer),
+            from_writer,
+            buffer: Some(Vec::new()),
+            timing: None,
+            writer: Some(writer),
+        }
+    }
+}
+
+fn writer_stopped() -> io::Error {
+    io::Error::new(io::ErrorKind::BrokenPipe, "LED writer thread stopped")
+}
+
+impl LedSink for ThreadedSink {
+    fn write_frame(&mut self, frame: &[RGB48]) -> io::Result<()> {
+        let mut buffer = match self.buffer.take() {
+            Some(buffer) => buffer,
+            None => {
+                let (buffer, result) = self.from_writer.recv().map_err(|_| writer_stopped())?;
+                match result {
+                    Ok(timing) => self.timing = Some(timing),
+                    Err(e) => {
+                        self.buffer = Some(buffer);
+                        return Err(e);
+                    }
+                }
+                buffer
+            }
+        };
+
+        buffer.clear();
+        buffer.extend_from_slice(frame);
+
+        self.to_writer
+            .as_ref()
+            .ok_or_else(writer_stopped)?
+            .send((buffer, Instant::now()))
+            .map_err(|_| writer_stopped())
+    }
+
+    fn last_timing(&self) -> Option<WireTiming> {
+        self.timing
+    }
+}
+
+impl Drop for ThreadedSink {
+    /// Waits for the last frame to be written, so that it is not lost
+    /// when the program ends.
+    fn drop(&mut self) {
+        self.to_writer = None;
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+        }
+    }
+}
+
+/// NullSink discards every frame, for running without LED hardware
+pub struct NullSink;
+
+impl LedSink for NullSink {
+    fn write_frame(&mut self, _frame: &[RGB48]) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// CaptureSink keeps every frame in memory. Clones share the same store,
+/// so a clone kept back before handing the sink to WheelLEDs can be used
+/// to look at what was shown.
+#[derive(Clone, Default)]
+pub struct CaptureSink {
+    frames: Arc<Mutex<Vec<Vec<RGB48>>>>,
+}
+
+impl CaptureSink {
+    pub fn new() -> CaptureSink {
+        Default::default()
+    }
+
+    /// Removes and returns all frames captured so far, oldest first
+    pub fn take_frames(&self) -> Vec<Vec<RGB48>> {
+        let mut frames = self.frames.lock().expect("capture sink lock");
+        std::mem::take(&mut *frames)
+    }
+}
+
+impl LedSink for CaptureSink {
+    fn write_frame(&mut self, frame: &[RGB48]) -> io::Result<()> {
+        let mut frames = self.frames.lock().expect("capture sink lock");
+        frames.push(frame.to_vec());
+        Ok(())
+    }
+}
+
+/// Picks the sink for WheelLEDs::new. The RUSTY_WHEELS_LEDS environment
+/// variable can be set to "null" to run without hardware, which is the
+/// default when not built for a Pi; otherwise SPI is used from a writer
+/// thread, and panics if it cannot be set up, as the wheel would be dark.
+fn default_sink(layout: &Layout, chipset: Chipset) -> Box<dyn LedSink> {
+    let on_pi = cfg!(any(target_arch = "arm", target_arch = "aarch64"));
+    match env::var("RUSTY_WHEELS_LEDS").as_deref() {
+        Ok("null") => {
+            println!("Using null LED sink");
+            Box::new(NullSink)
+        }
+        Err(_) if !on_pi => {
+            println!("Not running on a Pi - using null LED sink");
+            Box::new(NullSink)
+        }
+        _ => match SpiSink::new(chipset, layout.channel_orders()) {
+            Ok(spi) => Box::new(ThreadedSink::new(Box::new(spi))),
+            Err(e) => panic!("LED setup returned an error: {}", e),
+        },
+    }
+}
+
+/// Calibration describes how the colours stored by modes, which are
+/// perceptually even, are turned into the light levels sent to the strip.
+/// Strips from different batches differ, so these can be set for each
+/// wheel with the RUSTY_WHEELS_GAMMA, RUSTY_WHEELS_WHITE_BALANCE and
+/// RUSTY_WHEELS_BRIGHTNESS environment variables.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Calibration {
+    /// exponent applied to each channel, from 0..1 stored values to 0..1
+    /// light output. 1.0 sends stored values unchanged.
+    pub gamma: f32,
+
+    /// scale for each of red, green and blue, applied after gamma, to
+    /// balance strips with a tint
+    pub white_balance: (f32, f32, f32),
+
+    /// scale applied to every channel, after gamma
+    pub brightness: f32,
+}
+
+impl Default for Calibration {
+    fn default() -> Calibration {
+        Calibration {
+            gamma: 2.2,
+            white_balance: (1.0, 1.0, 1.0),
+            brightness: 1.0,
+        }
+    }
+}
+
+impl Calibration {
+    /// The default calibration, with any of its settings overridden by
+    /// environment variables. RUSTY_WHEELS_WHITE_BALANCE takes three
+    /// comma separated scales, red first.
+    pub fn from_env() -> Calibration {
+        let mut calibration = Calibration::default();
+
+        if let Ok(v) = env::var("RUSTY_WHEELS_GAMMA") {
+            calibration.gamma = v.parse().expect("parseable RUSTY_WHEELS_GAMMA");
+        }
+
+        if let Ok(v) = env::var("RUSTY_WHEELS_WHITE_BALANCE") {
+            let scales: Vec<f32> = v
+                .split(',')
+                .map(|s| {
+                    s.trim()
+                        .parse()
+                        .expect("parseable RUSTY_WHEELS_WHITE_BALANCE")
+                })
+                .collect();
+            match scales[..] {
+                [r, g, b] => calibration.white_balance = (r, g, b),
+                _ => panic!("RUSTY_WHEELS_WHITE_BALANCE needs three values"),
+            }
+        }
+
+        if let Ok(v) = env::var("RUSTY_WHEELS_BRIGHTNESS") {
+            calibration.brightness = v.parse().expect("parseable RUSTY_WHEELS_BRIGHTNESS");
+        }
+
+        calibration
+    }
+}
+
+/// The number of steps in each calibration lookup table. Values between
+/// steps are interpolated.
+const CURVE_STEPS: usize = 1024;
+
+/// A lookup table from a stored channel value to the calibrated light
+/// level, so that show() does not need to call powf for every LED.
+struct Curve {
+    table: [u16; CURVE_STEPS + 1],
+}
+
+impl Curve {
+    fn new(gamma: f32, scale: f32) -> Curve {
+        let mut table = [0; CURVE_STEPS + 1];
+        for (step, entry) in table.iter_mut().enumerate() {
+            let x = step as f32 / CURVE_STEPS as f32;
+            let y = (x.powf(gamma) * scale).clamp(0.0, 1.0);
+            *entry = (y * 65535.0).round() as u16;
+        }
+        Curve { table }
+    }
+
+    fn apply(&self, v: u16) -> u16 {
+        // 64 stored values per step
+        let step = v as usize >> 6;
+        let frac = (v & 63) as u32;
+        let low = self.table[step] as u32;
+        let high = self.table[cmp::min(step + 1, CURVE_STEPS)] as u32;
+        ((low * (64 - frac) + high * frac + 32) / 64) as u16
+    }
+}
+
+/// The calibration curves for red, green and blue
+struct Curves {
+    r: Curve,
+    g: Curve,
+    b: Curve,
+}
+
+impl Curves {
+    fn new(calibration: &Calibration) -> Curves {
+        let (r, g, b) = calibration.white_balance;
+        let gamma = calibration.gamma;
+        let brightness = calibration.brightness;
+        Curves {
+            r: Curve::new(gamma, r * brightness),
+            g: Curve::new(gamma, g * brightness),
+            b: Curve::new(gamma, b * brightness),
+        }
+    }
+
+    fn apply(&self, rgb: RGB48) -> RGB48 {
+        let (r, g, b) = rgb;
+        (self.r.apply(r), self.g.apply(g), self.b.apply(b))
+    }
+}
+
+/// PowerLimit describes a simple model of the current drawn by the strip,
+/// and the most current that the supply can give it. It can be set for
+/// each wheel with the RUSTY_WHEELS_MA_PER_CHANNEL,
+/// RUSTY_WHEELS_IDLE_MA_PER_LED and RUSTY_WHEELS_CURRENT_BUDGET_MA
+/// environment variables.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PowerLimit {
+    /// current drawn by one colour channel of one LED at full brightness,
+    /// in mA. Current is assumed to be proportional to light output.
+    pub ma_per_channel: f32,
+
+    /// current drawn by each LED even when it is dark, in mA
+    pub idle_ma_per_led: f32,
+
+    /// the most current the whole strip may draw, in mA. Frames estimated
+    /// to draw more than this are dimmed until they fit.
+    pub budget_ma: f32,
+}
+
+impl Default for PowerLimit {
+    fn default() -> PowerLimit {
+        PowerLimit {
+            ma_per_channel: 20.0,
+            idle_ma_per_led: 1.0,
+            budget_ma: 2000.0,
+        }
+    }
+}
+
+impl PowerLimit {
+    /// The default power limit, with any of its settings overridden by
+    /// environment variables.
+    pub fn from_env() -> PowerLimit {
+        let mut limit = PowerLimit::default();
+
+        if let Ok(v) = env::var("RUSTY_WHEELS_MA_PER_CHANNEL") {
+            limit.ma_per_channel = v.parse().expect("parseable RUSTY_WHEELS_MA_PER_CHANNEL");
+        }
+
+        if let Ok(v) = env::var("RUSTY_WHEELS_IDLE_MA_PER_LED") {
+            limit.idle_ma_per_led = v.parse().expect("parseable RUSTY_WHEELS_IDLE_MA_PER_LED");
+        }
+
+        if let Ok(v) = env::var("RUSTY_WHEELS_CURRENT_BUDGET_MA") {
+            limit.budget_ma = v.parse().expect("parseable RUSTY_WHEELS_CURRENT_BUDGET_MA");
+        }
+
+        limit
+    }
+
+    /// Estimates the current drawn by a frame of light levels, in mA
+    fn estimate(&self, frame: &[RGB48]) -> f32 {
+        let total: u32 = frame
+            .iter()
+            .map(|(r, g, b)| *r as u32 + *g as u32 + *b as u32)
+            .sum();
+        frame.len() as f32 * self.idle_ma_per_led + total as f32 / 65535.0 * self.ma_per_channel
+    }
+
+    /// Dims frame, if needed, so that it fits within the budget. All
+    /// channels are scaled by the same amount so that colours do not
+    /// change hue. Returns the estimated current before and after.
+    fn limit(&self, frame: &mut [RGB48]) -> PowerEstimate {
+        let estimated_ma = self.estimate(frame);
+
+        if estimated_ma <= self.budget_ma {
+            return PowerEstimate {
+                estimated_ma,
+                limited_ma: estimated_ma,
+            };
+        }
+
+        // idle current cannot be dimmed away
+        let idle_ma = frame.len() as f32 * self.idle_ma_per_led;
+        let scale = ((self.budget_ma - idle_ma) / (estimated_ma - idle_ma)).clamp(0.0, 1.0);
+
+        let dim = |v: u16| (v as f32 * scale) as u16;
+        for (r, g, b) in frame.iter_mut() {
+            *r = dim(*r);
+            *g = dim(*g);
+            *b = dim(*b);
+        }
+
+        PowerEstimate {
+            estimated_ma,
+            limited_ma: self.estimate(frame),
+        }
+    }
+}
+
+/// The current drawn by the most recently shown frame, estimated using
+/// the PowerLimit model
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PowerEstimate {
+    /// the current the frame would have drawn as rendered, in mA
+    pub estimated_ma: f32,
+
+    /// the current drawn after dimming to fit the budget, in mA
+    pub limited_ma: f32,
+}
+
+impl PowerEstimate {
+    /// true if the frame had to be dimmed
+    pub fn limited(&self) -> bool {
+        self.limited_ma < self.estimated_ma
+    }
+}
+
+/// A Side identifies one arm of LEDs on the physical wheel, numbered in
+/// the order the arms are listed in the Layout.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub struct Side(pub usize);
+
+impl Side {
+    /// The first arm in the layout, which was the left side of the bike
+    /// before the layout allowed other arms
+    pub const LEFT: Side = Side(0);
+    /// The second arm in the layout
+    pub const RIGHT: Side = Side(1);
 }
 
-/// A Side identifies a side of the physical wheel
-#[derive(PartialEq, Eq, Copy, Clone)]
-pub enum Side {
-    /// The first set of LEDs on the string
-    Left,
-    /// The second set of LEDs on the string
-    Right,
+/// PhysicalLED says where along its arm's stored values one LED on the
+/// chain takes its colour from, and any correction for that LED.
+struct PhysicalLED {
+    arm: usize,
+
+    /// position in stored values, from 0 at the hub to LEDS - 1 at the
+    /// rim. This falls between stored values when an arm does not have
+    /// LEDS physical LEDs, or has dead LEDs skipped.
+    position: f32,
+
+    /// scale applied to the LED's light output, 1.0 for most LEDs
+    scale: f32,
+
+    /// false for a dead LED which is always sent black
+    lit: bool,
 }
 
-pub const SIDES: [Side; 2] = [Side::Left, Side::Right];
+/// The physical LEDs on one arm, starting at the hub, with stored values
+/// spread across them and dead LEDs and brightness corrections dealt with
+/// as the ArmLayout says.
+fn physical_arm(side: Side, arm: &ArmLayout, stored: usize) -> Vec<PhysicalLED> {
+    let spread = |n: usize, count: usize| {
+        if count > 1 {
+            n as f32 * (stored - 1) as f32 / (count - 1) as f32
+        } else {
+            0.0
+        }
+    };
+
+    let mut leds: Vec<PhysicalLED> = (0..arm.leds)
+        .map(|led| PhysicalLED {
+            arm: side.0,
+            position: spread(led, arm.leds),
+            scale: 1.0,
+            lit: true,
+        })
+        .collect();
+
+    let working: Vec<usize> = (0..arm.leds)
+        .filter(|led| !arm.dead_leds.contains(led))
+        .collect();
+
+    match arm.dead_fill {
+        DeadFill::Skip => {
+            for (n, led) in working.iter().enumerate() {
+                leds[*led].position = spread(n, working.len());
+            }
+            for led in arm.dead_leds.iter() {
+                leds[*led].lit = false;
+            }
+        }
+        DeadFill::Mirror => {
+            for led in arm.dead_leds.iter() {
+                // the nearest working LED, taking the one nearer the hub
+                // if two are as near
+                if let Some(nearest) = working.iter().min_by_key(|w| w.abs_diff(*led)) {
+                    leds[*led].position = leds[*nearest].position;
+                }
+            }
+        }
+    }
+
+    for correction in arm.led_brightness.iter() {
+        leds[correction.led].scale *= correction.scale;
+    }
+
+    leds
+}
 
 /// WheelLEDs provides some kind of array-like access to setting individual
 /// LEDs which can then be dumped out in one frame.
 /// It provides a mutable collection of RGB tuples, one entry for each LED,
 /// structure in two dimensions by radial position and side
 /// and a way to dump that array onto the physical LED array.
-
+/// Colours are stored with 16 bits per channel so that dim colours can be
+/// shown precisely; set takes ordinary 8-bit colours and set16 takes the
+/// full precision.
+/// Stored colours are perceptual, like ordinary RGB values, and are
+/// turned into light levels by the Calibration as they are shown, so
+/// modes should not apply their own gamma.
+/// Every arm stores LEDS values, whatever the Layout says is physically
+/// there, and show() resamples arms with a different number of LEDs.
+/// show() also leaves out or covers for the LEDs that the Layout lists as
+/// dead, and applies its per-LED brightness corrections.
 pub struct WheelLEDs<const LEDS: usize> {
-    led_stream: BufWriter<Spidev>,
+    sink: Box<dyn LedSink>,
+
+    layout: Layout,
+
+    /// physical lists every LED in chain order
+    physical: Vec<PhysicalLED>,
+
+    calibration: Calibration,
+    curves: Curves,
 
-    /// left_leds stores RGB values for the left side of the wheel,
-    /// starting at the centre.
-    left_leds: [RGB24; LEDS],
+    power_limit: PowerLimit,
+    power: PowerEstimate,
 
-    /// right_leds stores RGB values for the right side fo the wheel,
-    /// starting at the centre. This is the reverse of the order
-    /// that right-side LEDs need to be sent down SPI.
-    right_leds: [RGB24; LEDS],
+    timing: Option<WireTiming>,
+
+    /// frame is reused on each show() to assemble LED values in
+    /// physical chain order for the sink.
+    frame: Vec<RGB48>,
+
+    /// arm_leds stores RGB values for each arm of the wheel, starting
+    /// at the centre.
+    arm_leds: Vec<[RGB48; LEDS]>,
 }
 
 impl<const LEDS: usize> WheelLEDs<LEDS> {
-    /// set a pixel, side 0 or 1, pixel 0 ... LEDS-1
-    /// pixel number starts at the centre of the wheel, on both
+    /// set a pixel on one side, pixel 0 ... LEDS-1
+    /// pixel number starts at the centre of the wheel, on all
     /// sides.
     pub fn set(&mut self, side: Side, pixel: usize, rgb: RGB24) {
-        assert!(pixel < LEDS, "pixel number too large");
-        match side {
-            Side::Left => self.left_leds[pixel] = rgb,
-            Side::Right => self.right_leds[pixel] = rgb,
-        }
+        self.set16(side, pixel, widen(rgb));
     }
 
-    pub fn side_slice(&mut self, side: Side) -> &mut [RGB24] {
-        match side {
-            Side::Left => &mut self.left_leds,
-            Side::Right => &mut self.right_leds,
-        }
+    /// set a pixel to a light level, rather than a perceptual colour, so
+    /// that 1 is 1/255 of full light whatever the gamma. The stopped
+    /// effects were written as light levels, and their dim reds would be
+    /// lost if stored as perceptual colours.
+    pub fn set_linear(&mut self, side: Side, pixel: usize, rgb: RGB24) {
+        self.set_linear16(side, pixel, widen(rgb));
     }
 
-    pub fn side_slice_b(&mut self, side: Side) -> &mut [RGB24; LEDS] {
-        match side {
-            Side::Left => &mut self.left_leds,
-            Side::Right => &mut self.right_leds,
-        }
+    /// set a pixel to a light level with 16 bits per channel, otherwise
+    /// like set_linear, for fades which need finer steps
+    pub fn set_linear16(&mut self, side: Side, pixel: usize, rgb: RGB48) {
+        let gamma = self.calibration.gamma;
+        let c = |v: u16| ((v as f32 / 65535.0).powf(1.0 / gamma) * 65535.0).round() as u16;
+        let (r, g, b) = rgb;
+        self.set16(side, pixel, (c(r), c(g), c(b)));
     }
 
-    /// Writes the stored LED values to the physical strip over SPI
-    pub fn show(&mut self) -> io::Result<()> {
-        // initialise LED strip to recieve values from the start
-        send_led(&mut self.led_stream, 0, 0, 0, 0)?;
+    /// set a pixel with 16 bits per channel, otherwise like set
+    pub fn set16(&mut self, side: Side, pixel: usize, rgb: RGB48) {
+        assert!(pixel < LEDS, "pixel number too large");
+        self.arm_leds[side.0][pixel] = rgb;
+    }
+
+    /// Runs f over an 8-bit copy of one side's LEDs, and then stores
+    /// back any that f changed. This is for render functions which work
+    /// on plain RGB24 slices.
+    pub fn with_side_slice<R>(&mut self, side: Side, f: impl FnOnce(&mut [RGB24; LEDS]) -> R) -> R {
+        let stored = &mut self.arm_leds[side.0];
+
+        let before: [RGB24; LEDS] = stored.map(narrow);
+        let mut after = before;
+
+        let result = f(&mut after);
 
         for led in 0..LEDS {
-            send_rgb(&mut self.led_stream, self.left_leds[led])?;
+            if after[led] != before[led] {
+                stored[led] = widen(after[led]);
+            }
         }
 
-        for led in 0..LEDS {
-            send_rgb(&mut self.led_stream, self.right_leds[LEDS - 1 - led])?;
+        result
+    }
+
+    /// Every side of the wheel, in layout order
+    pub fn sides(&self) -> impl Iterator<Item = Side> {
+        (0..self.arm_leds.len()).map(Side)
+    }
+
+    pub fn layout(&self) -> &Layout {
+        &self.layout
+    }
+
+    /// Writes the stored LED values to the physical strip via the sink.
+    /// Unless the sink times its own frames, the time it takes is measured
+    /// on clock, so that under a ManualClock it takes none.
+    pub fn show(&mut self, clock: &dyn Clock) -> io::Result<()> {
+        self.frame.clear();
+
+        for led in self.physical.iter() {
+            if !led.lit {
+                self.frame.push((0, 0, 0));
+                continue;
+            }
+
+            let rgb = self
+                .curves
+                .apply(sample(&self.arm_leds[led.arm], led.position));
+            self.frame.push(if led.scale == 1.0 {
+                rgb
+            } else {
+                scale(rgb, led.scale)
+            });
         }
 
-        // padding for clocking purposes down-strip
-        send_led(&mut self.led_stream, 0, 0, 0, 0)?;
-        send_led(&mut self.led_stream, 0, 0, 0, 0)?;
-        send_led(&mut self.led_stream, 0, 0, 0, 0)?;
-        send_led(&mut self.led_stream, 0, 0, 0, 0)?;
+        self.power = self.power_limit.limit(&mut self.frame);
+
+        let handed_over = clock.now();
+        self.sink.write_frame(&self.frame)?;
 
-        self.led_stream.flush()?;
+        self.timing = match self.sink.last_timing() {
+            Some(timing) => Some(timing),
+            None => Some(WireTiming {
+                handed_over,
+                written: clock.now(),
+            }),
+        };
 
         Ok(())
     }
 
-    pub fn new() -> WheelLEDs<LEDS> {
-        let led_stream = match setup_leds() {
-            Ok(leds) => leds,
-            Err(e) => panic!("LED setup returned an error: {e}"),
-        };
+    /// How long the most recently written frame took between show() and
+    /// the LEDs. When frames are written on another thread, this is for
+    /// an earlier frame than the one just shown.
+    pub fn latency(&self) -> Duration {
+        self.timing.map(|t| t.latency()).unwrap_or(Duration::ZERO)
+    }
+
+    pub fn calibration(&self) -> Calibration {
+        self.calibration
+    }
+
+    /// Changes the calibration used by subsequent calls to show()
+    pub fn set_calibration(&mut self, calibration: Calibration) {
+        self.calibration = calibration;
+        self.curves = Curves::new(&calibration);
+    }
+
+    /// Changes the angle_offset of one arm in the layout
+    pub fn set_angle_offset(&mut self, side: Side, angle_offset: f32) {
+        self.layout.arms[side.0].angle_offset = angle_offset;
+    }
+
+    pub fn power_limit(&self) -> PowerLimit {
+        self.power_limit
+    }
+
+    /// Changes the power limit used by subsequent calls to show()
+    pub fn set_power_limit(&mut self, power_limit: PowerLimit) {
+        self.power_limit = power_limit;
+    }
+
+    /// The estimated current for the most recently shown frame
+    pub fn power(&self) -> PowerEstimate {
+        self.power
+    }
+
+    pub fn new(layout: Layout, chipset: Chipset) -> WheelLEDs<LEDS> {
+        let sink = default_sink(&layout, chipset);
+        WheelLEDs::with_sink(layout, sink)
+    }
 
+    pub fn with_sink(layout: Layout, sink: Box<dyn LedSink>) -> WheelLEDs<LEDS> {
+        let mut physical = Vec::with_capacity(layout.total_leds());
+        for side in layout.wire_order() {
+            let arm = layout.arm(side);
+            let mut leds = physical_arm(side, arm, LEDS);
+            if arm.direction == ChainDirection::Inwards {
+                leds.reverse();
+            }
+            physical.extend(leds);
+        }
+
+        let calibration = Calibration::from_env();
         WheelLEDs {
-            led_stream,
-            left_leds: [(0, 0, 0); LEDS],
-            right_leds: [(0, 0, 0); LEDS],
+            sink,
+            arm_leds: vec![[(0, 0, 0); LEDS]; layout.arms.len()],
+            frame: Vec::with_capacity(physical.len()),
+            physical,
+            layout,
+            calibration,
+            curves: Curves::new(&calibration),
+            power_limit: PowerLimit::from_env(),
+            power: Default::default(),
+            timing: None,
+        }
+    }
+}
+
+/// Reads a colour from part way along an arm's stored values, blending
+/// the two nearest.
+fn sample<const LEDS: usize>(leds: &[RGB48; LEDS], position: f32) -> RGB48 {
+    let low = (position.floor() as usize).min(LEDS - 1);
+    let frac = position - low as f32;
+    if frac <= 0.0 || low + 1 >= LEDS {
+        return leds[low];
+    }
+
+    let (r0, g0, b0) = leds[low];
+    let (r1, g1, b1) = leds[low + 1];
+    let mix = |a: u16, b: u16| (a as f32 + (b as f32 - a as f32) * frac).round() as u16;
+    (mix(r0, r1), mix(g0, g1), mix(b0, b1))
+}
+
+/// Scales a light level, saturating at full brightness
+fn scale(rgb: RGB48, scale: f32) -> RGB48 {
+    let (r, g, b) = rgb;
+    let s = |v: u16| (v as f32 * scale).round().min(65535.0) as u16;
+    (s(r), s(g), s(b))
+}
+
+/// Converts an 8-bit colour to 16 bits, so that 255 becomes 65535
+pub fn widen(rgb: RGB24) -> RGB48 {
+    let (r, g, b) = rgb;
+    (r as u16 * 257, g as u16 * 257, b as u16 * 257)
+}
+
+/// Converts a 16-bit colour to the nearest 8-bit colour
+pub fn narrow(rgb: RGB48) -> RGB24 {
+    let (r, g, b) = rgb;
+    let n = |v: u16| ((v as u32 + 128) / 257) as u8;
+    (n(r), n(g), n(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::layout::LedBrightness;
+
+    /// The bytes a Ws2812Frame sends for one channel value
+    fn ws2812_channel(v: u8) -> Vec<u8> {
+        let mut encoded = Ws2812Frame::new(false);
+        encoded.push_channel(v);
+        encoded.bytes
+    }
+
+    /// The length of the low reset gap, at three SPI bits per 1.25us
+    const RESET_BYTES: usize = 90;
+
+    #[test]
+    fn ws2812_bits_are_sent_as_spi_symbols() {
+        // 0 is 100 and 1 is 110, most significant bit first
+        assert_eq!(ws2812_channel(0x00), [0x92, 0x49, 0x24]);
+        assert_eq!(ws2812_channel(0xFF), [0xDB, 0x6D, 0xB6]);
+        // 1 0 1 0 0 1 0 1 is 110 100 110 100 100 110 100 110
+        assert_eq!(ws2812_channel(0xA5), [0xD3, 0x49, 0xA6]);
+    }
+
+    #[test]
+    fn ws2812_sends_green_first_and_ends_with_a_reset_gap() {
+        let mut encoded = Ws2812Frame::new(false);
+        encoded.encode(&[widen((0xFF, 0xA5, 0x00))]);
+
+        let mut expected = Vec::new();
+        for v in [0xA5, 0xFF, 0x00] {
+            expected.extend(ws2812_channel(v));
+        }
+        expected.resize(expected.len() + RESET_BYTES, 0);
+        assert_eq!(encoded.as_bytes(), expected);
+
+        encoded.encode(&[]);
+        assert_eq!(encoded.as_bytes(), [0; RESET_BYTES]);
+    }
+
+    #[test]
+    fn sk6812_takes_white_from_the_common_part() {
+        let mut encoded = Ws2812Frame::new(true);
+        encoded.encode(&[widen((200, 150, 100))]);
+
+        let mut expected = Vec::new();
+        for v in [50, 100, 0, 100] {
+            expected.extend(ws2812_channel(v));
+        }
+        expected.resize(expected.len() + RESET_BYTES, 0);
+        assert_eq!(encoded.as_bytes(), expected);
+    }
+
+    #[test]
+    fn apa102_frame_layout() {
+        let mut encoded = Apa102Frame::new();
+        encoded.encode(&[(65535, 0, 0), (0, 0, 65535)]);
+
+        assert_eq!(
+            encoded.as_bytes(),
+            [
+                0, 0, 0, 0, // start frame
+                0xFF, 0, 0, 255, // a red LED: brightness, blue, green, red
+                0xFF, 255, 0, 0, // a blue LED
+                0, 0, 0, 0, // end frame
+            ]
+        );
+    }
+
+    #[test]
+    fn apa102_end_frame_is_half_a_bit_per_led() {
+        let mut encoded = Apa102Frame::new();
+        for (leds, end_bytes) in [(0, 4), (1, 4), (64, 4), (65, 5), (100, 7), (144, 9)] {
+            let frame = vec![(0, 0, 0); leds];
+            encoded.encode(&frame);
+
+            let bytes = encoded.as_bytes();
+            assert_eq!(bytes.len(), 4 + 4 * leds + end_bytes, "{leds} LEDs");
+            assert!(bytes[4 + 4 * leds..].iter().all(|b| *b == 0), "{leds} LEDs");
         }
     }
+
+    /// An arm of leds LEDs with the given dead LEDs
+    fn arm_with_dead(leds: usize, dead_leds: Vec<usize>, dead_fill: DeadFill) -> ArmLayout {
+        let mut arm = Layout::default().arms[1].clone();
+        arm.leds = leds;
+        arm.dead_leds = dead_leds;
+        arm.dead_fill = dead_fill;
+        arm
+    }
+
+    fn positions(leds: &[PhysicalLED]) -> Vec<f32> {
+        leds.iter().map(|led| led.position).collect()
+    }
+
+    #[test]
+    fn skipped_dead_leds_are_dark_and_spread_over() {
+        let arm = arm_with_dead(5, vec![2], DeadFill::Skip);
+        let leds = physical_arm(Side::RIGHT, &arm, 5);
+
+        let lit: Vec<bool> = leds.iter().map(|led| led.lit).collect();
+        assert_eq!(lit, [true, true, false, true, true]);
+
+        // the four working LEDs cover every stored value from hub to rim
+        let working: Vec<f32> = leds
+            .iter()
+            .filter(|led| led.lit)
+            .map(|led| led.position)
+            .collect();
+        assert_eq!(working, [0.0, 4.0 / 3.0, 8.0 / 3.0, 4.0]);
+        assert!(leds.iter().all(|led| led.arm == 1));
+    }
+
+    #[test]
+    fn mirrored_dead_leds_copy_the_nearest_working_led() {
+        let arm = arm_with_dead(6, vec![1, 3, 4], DeadFill::Mirror);
+        let leds = physical_arm(Side::RIGHT, &arm, 6);
+
+        // 1 is between 0 and 2 and takes the hub side, 3 is next to 2,
+        // and 4 is next to 5
+        assert_eq!(positions(&leds), [0.0, 0.0, 2.0, 2.0, 5.0, 5.0]);
+        assert!(leds.iter().all(|led| led.lit));
+    }
+
+    #[test]
+    fn leds_are_spread_over_the_stored_values() {
+        let arm = arm_with_dead(3, Vec::new(), DeadFill::Skip);
+        assert_eq!(
+            positions(&physical_arm(Side::RIGHT, &arm, 5)),
+            [0.0, 2.0, 4.0]
+        );
+
+        let arm = arm_with_dead(1, Vec::new(), DeadFill::Skip);
+        assert_eq!(positions(&physical_arm(Side::RIGHT, &arm, 5)), [0.0]);
+    }
+
+    #[test]
+    fn led_brightness_scales_single_leds() {
+        let mut arm = arm_with_dead(4, Vec::new(), DeadFill::Skip);
+        arm.led_brightness = vec![
+            LedBrightness { led: 1, scale: 0.5 },
+            LedBrightness { led: 3, scale: 1.2 },
+        ];
+        let leds = physical_arm(Side::RIGHT, &arm, 4);
+
+        let scales: Vec<f32> = leds.iter().map(|led| led.scale).collect();
+        assert_eq!(scales, [1.0, 0.5, 1.0, 1.2]);
+
+        assert_eq!(scale((1000, 2000, 65535), 0.5), (500, 1000, 32768));
+        assert_eq!(scale((1000, 60000, 0), 1.2), (1200, 65535, 0));
+    }
 }
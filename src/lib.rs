@@ -1,7 +1,13 @@
 pub mod buttons;
+pub mod calibrate;
 pub mod chill_modes;
+pub mod clock;
+pub mod config;
+pub mod engine;
+pub mod gpio;
 pub mod helpers;
 pub mod jumble;
+pub mod layout;
 pub mod leds;
 pub mod magnet;
 pub mod mode_bitmap_text;
@@ -18,7 +24,12 @@ pub mod mode_randomwalk;
 pub mod mode_rgb_dither;
 pub mod mode_speckles;
 pub mod mode_stepper;
+pub mod mode_test_pattern;
 pub mod mode_trails;
 pub mod moving_modes;
+pub mod pov;
+pub mod rotation;
 pub mod stopped_modes;
 pub mod structs;
+pub mod timing;
+pub mod tui;
@@ -1,39 +1,248 @@
-use sysfs_gpio::{Direction, Edge, Pin};
+use std::io;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::Instant;
 
+use serde::Deserialize;
+
+use crate::gpio::{EdgeLine, GpioLine};
+
+/// The GPIO that the magnet sensor is wired to
+const MAGNET_GPIO: u32 = 27;
+
+/// How different, in revolutions, the gaps between magnets need to be for
+/// each magnet to be told apart by the gaps around it
+const MIN_GAP_DIFFERENCE: f64 = 0.05;
+
+/// MagnetLayout lists the angles of the magnets that pass the sensor, in
+/// revolutions from the angle that arm angles are measured from, in
+/// increasing order. More magnets give more frequent corrections to the
+/// estimated angle of the wheel, which matters most at low speed.
+/// With more than one magnet, they must be spaced unevenly, as each pulse
+/// is matched to a magnet by the pattern of gaps over the last revolution.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(transparent)]
+pub struct MagnetLayout {
+    pub angles: Vec<f32>,
+}
+
+impl Default for MagnetLayout {
+    /// One magnet, at angle 0
+    fn default() -> MagnetLayout {
+        MagnetLayout { angles: vec![0.0] }
+    }
+}
+
+impl MagnetLayout {
+    /// Checks that each magnet's pulses can be told apart
+    pub fn validate(&self) -> io::Result<()> {
+        let invalid = |msg: &str| Err(io::Error::new(io::ErrorKind::InvalidData, msg));
+
+        if self.angles.is_empty() {
+            return invalid("there are no magnets");
+        }
+
+        if self.angles.iter().any(|a| !(0.0..1.0).contains(a)) {
+            return invalid("magnet angles must be at least 0 and less than 1");
+        }
+
+        if self.angles.windows(2).any(|w| w[0] >= w[1]) {
+            return invalid("magnet angles must be in increasing order");
+        }
+
+        // the gaps must not look the same from any other magnet
+        let n = self.len();
+        for shift in 1..n {
+            let same = (0..n).all(|m| {
+                (self.gap_before(m) - self.gap_before((m + shift) % n)).abs() < MIN_GAP_DIFFERENCE
+            });
+            if same {
+                return invalid("magnets are spaced too evenly to be told apart");
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.angles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.angles.is_empty()
+    }
+
+    /// The angle of magnet n, in revolutions
+    pub fn angle(&self, n: usize) -> f64 {
+        self.angles[n] as f64
+    }
+
+    /// The angle from the magnet before magnet n to magnet n, in
+    /// revolutions
+    pub fn gap_before(&self, n: usize) -> f64 {
+        let before = (n + self.len() - 1) % self.len();
+        let gap = (self.angle(n) - self.angle(before)).rem_euclid(1.0);
+        if gap == 0.0 {
+            1.0
+        } else {
+            gap
+        }
+    }
+
+    /// The angle from magnet n to the magnet after it, in revolutions
+    pub fn gap_after(&self, n: usize) -> f64 {
+        self.gap_before((n + 1) % self.len())
+    }
+
+    /// The position of the first magnet after position, where positions
+    /// count revolutions from angle 0 on the first turn of the wheel
+    pub fn next_position(&self, position: f64) -> f64 {
+        let turn = position.floor();
+        self.angles
+            .iter()
+            .map(|a| turn + *a as f64)
+            .find(|p| *p > position)
+            .unwrap_or(turn + 1.0 + self.angle(0))
+    }
+
+    /// Works out which magnet made the latest of a run of pulses, from the
+    /// intervals between them, oldest first. The last revolution's worth
+    /// of intervals is compared with the gaps between the magnets, and the
+    /// magnet which fits best is returned. There need to be at least as
+    /// many intervals as magnets, or this returns None.
+    pub fn identify(&self, intervals: &[f64]) -> Option<usize> {
+        let n = self.len();
+        if n == 1 {
+            return Some(0);
+        }
+        if intervals.len() < n {
+            return None;
+        }
+
+        let recent = &intervals[intervals.len() - n..];
+        let total: f64 = recent.iter().sum();
+        if total <= 0.0 {
+            return None;
+        }
+
+        let mismatch = |latest: usize| -> f64 {
+            recent
+                .iter()
+                .enumerate()
+                .map(|(i, interval)| {
+                    // the last interval ends at the latest magnet
+                    let magnet = (latest + n - (n - 1 - i)) % n;
+                    (interval / total - self.gap_before(magnet)).powi(2)
+                })
+                .sum()
+        };
+
+        (0..n).min_by(|a, b| mismatch(*a).total_cmp(&mismatch(*b)))
+    }
+}
+
+/// A RotationSensor reports the magnet passing the sensor. Magnet is the
+/// real one; other implementations stand in for it off-bike.
+pub trait RotationSensor {
+    /// returns the time of the oldest magnet pass not yet returned, or
+    /// None if there are none. Callers should keep calling this until it
+    /// returns None, as the magnet may have passed more than once since
+    /// they last checked.
+    fn next_pulse(&mut self) -> Option<Instant>;
+}
+
+/// Magnet watches the magnet sensor from its own thread, which blocks
+/// waiting for each rising edge, so that pulse times do not depend on when
+/// the render loop gets round to checking.
 pub struct Magnet {
-    poller: sysfs_gpio::PinPoller,
+    pulses: Receiver<Instant>,
 }
 
 impl Magnet {
-    pub fn new() -> std::result::Result<Magnet, sysfs_gpio::Error> {
-        let poller = setup_magnet()?;
-        Ok(Magnet { poller })
+    pub fn new() -> io::Result<Magnet> {
+        println!("Configuring magnet");
+        let line = GpioLine::new(MAGNET_GPIO, "rusty-wheels magnet")?;
+        println!("Done configuring magnet");
+
+        Magnet::with_line(Box::new(line))
+    }
+
+    /// A Magnet which watches the given line, such as a FakeLine
+    pub fn with_line(line: Box<dyn EdgeLine>) -> io::Result<Magnet> {
+        let (sender, pulses) = mpsc::channel();
+
+        thread::Builder::new()
+            .name("magnet".to_string())
+            .spawn(move || watch_magnet(line, sender))?;
+
+        Ok(Magnet { pulses })
+    }
+}
+
+impl RotationSensor for Magnet {
+    fn next_pulse(&mut self) -> Option<Instant> {
+        self.pulses.try_recv().ok()
     }
+}
 
-    pub fn pulsed(&mut self) -> bool {
-        match self.poller.poll(0) {
-            Ok(Some(value)) => {
-                println!("Magnet poll got a pulse (value {value})");
-                true
+/// Runs on the magnet thread, sending the time of each rising edge. This
+/// stops if the line returns an error, or at the first pulse after the
+/// Magnet has been dropped.
+fn watch_magnet(mut line: Box<dyn EdgeLine>, pulses: Sender<Instant>) {
+    loop {
+        match line.wait_edge(None) {
+            Ok(Some(time)) => {
+                println!("Magnet got a pulse");
+                if pulses.send(time).is_err() {
+                    return;
+                }
+            }
+            Ok(None) => (),
+            Err(e) => {
+                println!("Magnet line returned an error: {e} - no more pulses");
+                return;
             }
-            _ => false,
         }
     }
 }
 
-fn setup_magnet() -> std::result::Result<sysfs_gpio::PinPoller, sysfs_gpio::Error> {
-    println!("Configuring magnet");
-    let pin = Pin::new(27);
-    pin.export()?;
-    pin.set_direction(Direction::In)?;
-    pin.set_edge(Edge::RisingEdge)?;
-    let mut poller: sysfs_gpio::PinPoller = pin.get_poller()?;
-    println!("Making first pin poll");
-    match poller.poll(0)? {
-        Some(value) => println!("Magnet poll got first value {value} - ignoring"),
-        None => (),
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Duration;
+
+    use crate::gpio::FakeLine;
+
+    /// Waits for the magnet thread to pass on a pulse
+    fn wait_for_pulse(magnet: &mut Magnet) -> Instant {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            if let Some(pulse) = magnet.next_pulse() {
+                return pulse;
+            }
+            assert!(Instant::now() < deadline, "no pulse from the magnet thread");
+            thread::sleep(Duration::from_millis(1));
+        }
     }
-    println!("Done configuring magnet");
 
-    Ok(poller)
+    #[test]
+    fn magnet_passes_on_edge_times_in_order() {
+        let (line, edges) = FakeLine::new();
+        let mut magnet = Magnet::with_line(Box::new(line)).unwrap();
+
+        // the edges happened earlier than they are read
+        let start = Instant::now() - Duration::from_secs(1);
+        let times: Vec<Instant> = (0..3)
+            .map(|n| start + Duration::from_millis(n * 300))
+            .collect();
+        for time in times.iter() {
+            edges.send(*time).unwrap();
+        }
+
+        for time in times.iter() {
+            assert_eq!(wait_for_pulse(&mut magnet), *time);
+        }
+        assert_eq!(magnet.next_pulse(), None);
+    }
 }
@@ -1,71 +1,67 @@
 use signal_hook::flag;
 
-use std::cmp;
 use std::env;
 use std::io;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use std::thread;
-
-use std::time::{Duration, Instant};
-
-use rusty_wheels::chill_modes::render_chill_mode;
-use rusty_wheels::leds;
-use rusty_wheels::leds::{WheelLEDs, SIDES};
-use rusty_wheels::magnet::Magnet;
-use rusty_wheels::moving_modes::modes;
-use rusty_wheels::stopped_modes::render_caution_mode;
-use rusty_wheels::structs::{FrameState, Mode};
-
-use rusty_wheels::jumble::Jumbler;
 
 use rusty_wheels::buttons::PushButton;
-
-/// The duration between magnet pulses that distinguishes between
-/// stopped mode and live mode.
-const STOP_TIME_MS: u128 = 2000;
-
-/// The duration between mode changes.
-const MODE_CHANGE_SEC: u64 = 20;
-
-/// The number of LEDs on each side
-const N_LEDS: usize = 23;
-
-#[derive(PartialEq)]
-enum StoppedMode {
-    StoppedCaution,
-    StoppedWhite,
-    StoppedChill,
-}
-
-impl StoppedMode {
-    fn next(&self) -> StoppedMode {
-        match self {
-            StoppedMode::StoppedCaution => StoppedMode::StoppedWhite,
-            StoppedMode::StoppedWhite => StoppedMode::StoppedChill,
-            StoppedMode::StoppedChill => StoppedMode::StoppedCaution,
-        }
-    }
-}
+use rusty_wheels::clock::MonotonicClock;
+use rusty_wheels::config::Config;
+use rusty_wheels::engine::{Engine, EngineInputs, ForcedMode};
+use rusty_wheels::leds::WheelLEDs;
+use rusty_wheels::magnet::Magnet;
+use rusty_wheels::tui::Tui;
 
 fn main() {
     println!("Starting rusty-wheels");
 
-    let magnet = match Magnet::new() {
-        Ok(m) => m,
-        Err(e) => panic!("magnet setup returned an error: {}", e),
-    };
+    let mut args: Vec<String> = env::args().skip(1).collect();
 
-    let push_button = match PushButton::new() {
-        Ok(m) => m,
-        Err(e) => panic!("push button setup returned an error: {}", e),
-    };
+    // "tui" as the first argument draws the wheel in the terminal instead
+    // of on the LEDs, with keys standing in for the magnet and buttons
+    let tui = args.first().map(|a| a == "tui").unwrap_or(false);
+    if tui {
+        args.remove(0);
+    }
 
-    let wheel_leds: WheelLEDs<N_LEDS> = WheelLEDs::new();
+    // "--seed N" starts the modes' random numbers from N, to replay a mode
+    // whose seed was logged
+    let seed: Option<u64> = args.iter().position(|a| a == "--seed").map(|i| {
+        args.remove(i);
+        if i >= args.len() {
+            panic!("--seed needs a value on command line");
+        }
+        args.remove(i)
+            .parse()
+            .expect("parseable seed on command line")
+    });
+
+    let forced_mode: Option<ForcedMode> = args.first().map(|a| match a.as_str() {
+        "test-pattern" => ForcedMode::TestPattern,
+        "calibrate" => ForcedMode::Calibrate,
+        _ => ForcedMode::Index(a.parse().expect("parseable mode on command line")),
+    });
+
+    let config = match Config::load() {
+        Ok(c) => c,
+        Err(e) => panic!("config loading returned an error: {}", e),
+    };
 
     let shutdown_flag = Arc::new(AtomicBool::new(false));
 
-    match run_leds(magnet, wheel_leds, push_button, shutdown_flag) {
+    // modes are built for the LED counts of the wheels we have; other
+    // layouts are resampled from 23 LEDs
+    let result = match config.layout.max_leds() {
+        36 => start::<36>(config, tui, forced_mode, seed, shutdown_flag),
+        23 => start::<23>(config, tui, forced_mode, seed, shutdown_flag),
+        n => {
+            println!("No modes built for {n} LEDs per side - resampling from 23");
+            start::<23>(config, tui, forced_mode, seed, shutdown_flag)
+        }
+    };
+
+    match result {
         Ok(_) => println!("runleds finished ok"),
         Err(e) => println!("runleds returned an error: {}", e),
     }
@@ -73,142 +69,88 @@ fn main() {
     println!("Ending rusty-wheels");
 }
 
-fn run_leds<const LEDS: usize>(
-    mut magnet: Magnet,
-    mut wheel_leds: WheelLEDs<LEDS>,
-    mut push_button: PushButton,
+fn start<const LEDS: usize>(
+    config: Config,
+    tui: bool,
+    forced_mode: Option<ForcedMode>,
+    seed: Option<u64>,
     shutdown_flag: Arc<AtomicBool>,
 ) -> io::Result<()> {
-    let start_time = Instant::now();
-
-    let mut spin_start_time = start_time;
-    let mut last_spin_start_time = start_time;
-
-    for side in SIDES.iter() {
-        for led in 0..LEDS {
-            wheel_leds.set(*side, led, (0, 0, 0));
-        }
-    }
-    wheel_leds.show()?;
-
-    let args: Vec<String> = env::args().collect();
-
-    let mut loop_counter: u32 = 0;
-
-    flag::register(signal_hook::SIGTERM, Arc::clone(&shutdown_flag))?;
-    flag::register(signal_hook::SIGINT, Arc::clone(&shutdown_flag))?;
-
-    let mut floodlight: StoppedMode = StoppedMode::StoppedCaution;
+    if tui {
+        let tui = match Tui::start(Arc::clone(&shutdown_flag)) {
+            Ok(t) => t,
+            Err(e) => panic!("terminal setup returned an error: {}", e),
+        };
 
-    let mut next_mode_time = Instant::now();
+        let sink = Box::new(tui.sink(&config.layout));
+        let wheel_leds: WheelLEDs<LEDS> = WheelLEDs::with_sink(config.layout, sink);
 
-    let mut jumbler = Jumbler::new(modes().to_vec());
+        let inputs = EngineInputs {
+            magnet: Box::new(tui.magnet(&config.magnets)),
+            push_button: Box::new(tui.button(0)),
+            second_button: Box::new(tui.button(1)),
+            clock: Box::new(MonotonicClock),
+        };
 
-    // this is going to get replaced pretty much right away unless I implement a count-down timer mode switcher rather than
-    // absolute time based phasing. But it's better than threading Option behaviour all the way through.
-    let mut mode: Box<dyn Mode<LEDS>> = if args.len() <= 1 {
-        jumbler.next().unwrap()()
+        let engine = Engine::new(
+            inputs,
+            wheel_leds,
+            config.magnets,
+            config.wheel,
+            forced_mode,
+            seed,
+        )?;
+        run_leds(engine, shutdown_flag)
     } else {
-        let forced_mode: usize = args[1].parse().expect("parseable mode on command line");
-        (modes()[forced_mode])()
-    };
-
-    let mut stats_num_frames: u32 = 0;
-    let mut stats_start_time = Instant::now();
-
-    while !(shutdown_flag.load(Ordering::Relaxed)) {
-        if magnet.pulsed() {
-            last_spin_start_time = spin_start_time;
-            spin_start_time = Instant::now()
+        let magnet = match Magnet::new() {
+            Ok(m) => m,
+            Err(e) => panic!("magnet setup returned an error: {}", e),
         };
 
-        if push_button.pulsed() {
-            println!("push button pulse");
-            floodlight = floodlight.next();
-        }
-
-        let spin_length = spin_start_time - last_spin_start_time;
-
-        let mode_duration = cmp::max(spin_start_time.elapsed(), spin_length);
+        let push_button = match PushButton::new() {
+            Ok(m) => m,
+            Err(e) => panic!("push button setup returned an error: {}", e),
+        };
 
-        let framestate = FrameState {
-            now: start_time.elapsed(),
-            loop_counter: loop_counter,
-            spin_pos: (spin_start_time.elapsed().as_millis() as f32)
-                / (cmp::max(1, spin_length.as_millis()) as f32),
-            spin_length: spin_length,
+        let second_button = match PushButton::second() {
+            Ok(m) => m,
+            Err(e) => panic!("second push button setup returned an error: {}", e),
         };
 
-        if (mode_duration.as_millis() > STOP_TIME_MS || mode_duration.as_millis() == 0) && args.len() <= 1 {
-            match floodlight {
-                StoppedMode::StoppedCaution => render_caution_mode(&mut wheel_leds, &framestate),
-                StoppedMode::StoppedWhite => render_floodlight_mode(&mut wheel_leds, &framestate),
-                StoppedMode::StoppedChill => render_chill_mode(&mut wheel_leds, &framestate),
-            }?;
-        } else {
-            if next_mode_time <= Instant::now() && args.len() <= 1 {
-                mode = (jumbler.next().unwrap())();
-                next_mode_time = Instant::now() + Duration::from_secs(MODE_CHANGE_SEC);
-
-                let stats_duration = stats_start_time.elapsed();
-                let stats_fps = (stats_num_frames as f32) / (stats_duration.as_secs() as f32);
-                println!(
-                    "Frame rate statistics: {} frames over {:?} = {} frames/s",
-                    stats_num_frames, stats_duration, stats_fps
-                );
-                stats_num_frames = 0;
-                stats_start_time = Instant::now();
-            }
-
-            mode.pre_step(&framestate)?;
-            mode.render(leds::Side::Left, &mut wheel_leds, &framestate)?;
-            mode.render(leds::Side::Right, &mut wheel_leds, &framestate)?;
-            mode.step(&framestate)?;
-        }
+        let wheel_leds: WheelLEDs<LEDS> = WheelLEDs::new(config.layout, config.chipset);
 
-        wheel_leds.show()?;
+        let inputs = EngineInputs {
+            magnet: Box::new(magnet),
+            push_button: Box::new(push_button),
+            second_button: Box::new(second_button),
+            clock: Box::new(MonotonicClock),
+        };
 
-        loop_counter += 1;
-        stats_num_frames += 1;
+        let engine = Engine::new(
+            inputs,
+            wheel_leds,
+            config.magnets,
+            config.wheel,
+            forced_mode,
+            seed,
+        )?;
+        run_leds(engine, shutdown_flag)
     }
-    let duration_secs = start_time.elapsed().as_secs();
-    println!("Duration {} seconds", duration_secs);
+}
 
-    // run a shutdown effect
+fn run_leds<const LEDS: usize>(
+    mut engine: Engine<LEDS>,
+    shutdown_flag: Arc<AtomicBool>,
+) -> io::Result<()> {
+    flag::register(signal_hook::SIGTERM, Arc::clone(&shutdown_flag))?;
+    flag::register(signal_hook::SIGINT, Arc::clone(&shutdown_flag))?;
 
-    for side in SIDES.iter() {
-        for led in 0..LEDS {
-            wheel_leds.set(*side, led, (1, 1, 1));
-        }
+    while !(shutdown_flag.load(Ordering::Relaxed)) {
+        engine.frame()?;
     }
-    wheel_leds.show()?;
-
-    thread::sleep(Duration::from_millis(250));
 
-    for side in SIDES.iter() {
-        for led in 0..LEDS {
-            wheel_leds.set(*side, led, (0, 0, 0));
-        }
-    }
-    wheel_leds.show()?;
+    engine.shut_down()?;
 
     println!("ending");
     Ok(())
 }
-
-fn render_floodlight_mode<const LEDS: usize>(
-    wheel_leds: &mut WheelLEDs<LEDS>,
-    _framestate: &FrameState,
-) -> io::Result<()> {
-    for side in SIDES.iter() {
-        for led in 0..LEDS {
-            wheel_leds.set(*side, led, (32, 32, 32));
-        }
-        // override the middle ones with full brightness
-        for led in 9..14 {
-            wheel_leds.set(*side, led, (255, 255, 255));
-        }
-    }
-
-    Ok(())
-}
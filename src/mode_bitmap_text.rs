@@ -1,6 +1,7 @@
 use crate::leds;
 use crate::structs::{FrameState, Mode};
 use lazy_static::lazy_static;
+use rand::rngs::StdRng;
 use std::default::Default;
 use std::io;
 use std::time::Duration;
@@ -13,7 +14,7 @@ struct PhraseMode {
     bitmap: [u128; 7],
 }
 
-pub fn construct_phrase_mode<const LEDS: usize>() -> Box<dyn Mode<LEDS>> {
+pub fn construct_phrase_mode<const LEDS: usize>(_rng: &mut StdRng) -> Box<dyn Mode<LEDS>> {
     println!("Iniialising phrase bitmap");
     let phrase = "@BENCLIFFORD";
 
@@ -24,7 +25,7 @@ pub fn construct_phrase_mode<const LEDS: usize>() -> Box<dyn Mode<LEDS>> {
     Box::new(PhraseMode { bitmap })
 }
 
-pub fn construct_phrase_mode_hello<const LEDS: usize>() -> Box<dyn Mode<LEDS>> {
+pub fn construct_phrase_mode_hello<const LEDS: usize>(_rng: &mut StdRng) -> Box<dyn Mode<LEDS>> {
     println!("Iniialising phrase bitmap");
     let phrase = " HELLO  HELLO  HELLO ";
 
@@ -35,7 +36,7 @@ pub fn construct_phrase_mode_hello<const LEDS: usize>() -> Box<dyn Mode<LEDS>> {
     Box::new(PhraseMode { bitmap })
 }
 
-pub fn construct_phrase_fuck_boris<const LEDS: usize>() -> Box<dyn Mode<LEDS>> {
+pub fn construct_phrase_fuck_boris<const LEDS: usize>(_rng: &mut StdRng) -> Box<dyn Mode<LEDS>> {
     println!("Iniialising phrase bitmap");
     let phrase = " FUCK BORIS ";
 
@@ -62,10 +63,12 @@ struct SpeedoMode {
     last_change: Duration,
     last_spin_pos: f32,
     counter: u32,
-    render_text: fn(Duration) -> String,
+    /// makes the text from the time for one revolution, and the wheel
+    /// circumference in metres
+    render_text: fn(Duration, f32) -> String,
 }
 
-pub fn construct_speedo_mode_kmh<const LEDS: usize>() -> Box<dyn Mode<LEDS>> {
+pub fn construct_speedo_mode_kmh<const LEDS: usize>(_rng: &mut StdRng) -> Box<dyn Mode<LEDS>> {
     println!("Initialising speedo phrase bitmap: constructing phrase");
     let phrase = "  - KM/H";
 
@@ -83,7 +86,7 @@ pub fn construct_speedo_mode_kmh<const LEDS: usize>() -> Box<dyn Mode<LEDS>> {
     })
 }
 
-pub fn construct_speedo_mode_hz<const LEDS: usize>() -> Box<dyn Mode<LEDS>> {
+pub fn construct_speedo_mode_hz<const LEDS: usize>(_rng: &mut StdRng) -> Box<dyn Mode<LEDS>> {
     println!("Initialising speedo phrase bitmap: constructing phrase");
     let phrase = " - ";
 
@@ -101,7 +104,6 @@ pub fn construct_speedo_mode_hz<const LEDS: usize>() -> Box<dyn Mode<LEDS>> {
     })
 }
 
-
 impl<const LEDS: usize> Mode<LEDS> for SpeedoMode {
     fn render(
         &self,
@@ -122,7 +124,7 @@ impl<const LEDS: usize> Mode<LEDS> for SpeedoMode {
 
             let time_per_rot = frame.spin_length;
 
-            let phrase = (self.render_text)(time_per_rot);
+            let phrase = (self.render_text)(time_per_rot, frame.circumference_m);
 
             self.canvas.bitmap = str_to_bitmap(&phrase);
             self.counter += 1;
@@ -133,7 +135,7 @@ impl<const LEDS: usize> Mode<LEDS> for SpeedoMode {
     }
 }
 
-fn speedo_text_kmh(time_per_rot: Duration) -> String {
+fn speedo_text_kmh(time_per_rot: Duration, m_per_rot: f32) -> String {
     let s_per_rot: f32 = (time_per_rot.as_millis() as f32) / 1000.0;
 
     let h_per_rot: f32 = s_per_rot / 60.0 / 60.0;
@@ -144,12 +146,9 @@ fn speedo_text_kmh(time_per_rot: Duration) -> String {
     let phrase = if rot_per_hour.is_infinite() {
         "XXX km/h".to_string()
     } else {
-        // this is based on characteristics of the bike wheel
-        // which is 20" for my bike
-        const WHEEL_M_PER_ROT: f32 = 1.59;
-        const WHEEL_KM_PER_ROT: f32 = WHEEL_M_PER_ROT / 1000.0;
+        let km_per_rot = m_per_rot / 1000.0;
 
-        let kmh = WHEEL_KM_PER_ROT * rot_per_hour;
+        let kmh = km_per_rot * rot_per_hour;
 
         format!("{kmh:>3.0} km/h")
     };
@@ -157,7 +156,7 @@ fn speedo_text_kmh(time_per_rot: Duration) -> String {
     return phrase;
 }
 
-fn speedo_text_hz(time_per_rot: Duration) -> String {
+fn speedo_text_hz(time_per_rot: Duration, _m_per_rot: f32) -> String {
     let s_per_rot: f32 = (time_per_rot.as_millis() as f32) / 1000.0;
 
     let rot_per_s = 1.0 / s_per_rot;
@@ -184,7 +183,7 @@ fn helper_render_bitmap<const LEDS: usize>(
     }
     let mut pixel;
 
-    pixel = ((framestate.spin_pos % 1.0) * 128.0) as u8;
+    pixel = (framestate.angle(side) * 128.0) as u8;
 
     // if spin pos too high, maybe we'll go over a limit
 
@@ -192,17 +191,15 @@ fn helper_render_bitmap<const LEDS: usize>(
         pixel = 127;
     }
 
-    // flip pixels on other side because rotation is the other way round
-    if side == leds::Side::Left {
-        pixel = 127 - pixel;
-    }
-
-    for n in 0..7 {
+    // the text is drawn top row at the rim, so arms too short for all
+    // seven rows lose the bottom ones
+    for n in 0..LEDS.min(7) {
         let r = ((row[n] & (1 << pixel)) >> pixel) & 1;
         let colour = if r != 0 {
-            match side {
-                leds::Side::Left => (255, 32, 0),  // amber
-                leds::Side::Right => (56, 255, 0), // green - from wikipedia phosper wavelength converted to rgb
+            if side == leds::Side::LEFT {
+                (255, 32, 0) // amber
+            } else {
+                (56, 255, 0) // green - from wikipedia phosper wavelength converted to rgb
             }
         } else {
             (0, 0, 0)
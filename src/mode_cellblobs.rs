@@ -1,5 +1,6 @@
 use crate::leds;
 use crate::structs::{FrameState, Mode};
+use rand::rngs::StdRng;
 use rand::Rng;
 use std::io;
 use std::time::Duration;
@@ -9,11 +10,11 @@ struct CellBlobs<const LEDS: usize> {
     last_now: Duration,
 }
 
-pub fn create_cellblobs<const LEDS: usize>() -> Box<dyn Mode<LEDS>> {
+pub fn create_cellblobs<const LEDS: usize>(rng: &mut StdRng) -> Box<dyn Mode<LEDS>> {
     let mut cells = [false; LEDS];
 
     for n in 0..LEDS {
-        let r = rand::thread_rng().gen_range(0, 2);
+        let r = rng.gen_range(0, 2);
         cells[n] = r == 1;
     }
 
@@ -74,7 +75,7 @@ impl<const LEDS: usize> Mode<LEDS> for CellBlobs<LEDS> {
 
         self.last_now = frame.now;
 
-        let c = rand::thread_rng().gen_range(0, LEDS);
+        let c = frame.rng().gen_range(0, LEDS);
         self.cells[c] = !self.cells[c];
         Ok(())
     }
@@ -2,6 +2,7 @@ use crate::helpers::fraction_to_rgb;
 use crate::leds;
 use crate::structs::{FrameState, Mode, RGB24};
 use rand::prelude::SliceRandom;
+use rand::rngs::StdRng;
 use rand::Rng;
 use std::io;
 use std::time::Duration;
@@ -84,20 +85,20 @@ impl<const LEDS: usize> Mode<LEDS> for CellularState<LEDS> {
 /// These look good in rotating mode
 const PRETTY_AUTOMATA: &[u8] = &[18, 73, 105, 146];
 
-pub fn construct_cellular<const LEDS: usize>() -> Box<dyn Mode<LEDS>> {
+pub fn construct_cellular<const LEDS: usize>(rng: &mut StdRng) -> Box<dyn Mode<LEDS>> {
     let mut cells = [false; LEDS];
 
     for n in 0..LEDS {
-        let r = rand::thread_rng().gen_range(0, 2);
+        let r = rng.gen_range(0, 2);
         cells[n] = r == 1;
     }
 
-    let a_n = match PRETTY_AUTOMATA.choose(&mut rand::thread_rng()) {
+    let a_n = match PRETTY_AUTOMATA.choose(rng) {
         Some(x) => *x,
         None => panic!("Could not choose an automata number"),
     };
 
-    let hue = rand::thread_rng().gen_range(0.0, 1.0);
+    let hue = rng.gen_range(0.0, 1.0);
     let rgb = fraction_to_rgb(hue, Some(0.25));
 
     Box::new(CellularState {
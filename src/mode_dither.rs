@@ -1,24 +1,25 @@
 use crate::leds;
 use crate::structs::{FrameState, Mode, RGB24};
+use rand::rngs::StdRng;
 use std::io;
 
-struct Dither {
+struct Dither<const LEDS: usize> {
     /// This will contain the errors propagated from the previous frame
-    prev_errors: [f32; 23],
+    prev_errors: [f32; LEDS],
     /// This will contain the errors propagated to the next frame
-    next_errors: [f32; 23],
+    next_errors: [f32; LEDS],
     /// pre-step will render into here
-    rgb: [RGB24; 23],
+    rgb: [RGB24; LEDS],
 }
 
-impl<const LEDS: usize> Mode<LEDS> for Dither {
+impl<const LEDS: usize> Mode<LEDS> for Dither<LEDS> {
     fn render(
         &self,
         side: leds::Side,
         leds: &mut leds::WheelLEDs<LEDS>,
         _frame: &FrameState,
     ) -> io::Result<()> {
-        for led in 0..23 {
+        for led in 0..LEDS {
             leds.set(side, led, self.rgb[led]);
         }
         Ok(())
@@ -34,9 +35,9 @@ impl<const LEDS: usize> Mode<LEDS> for Dither {
         };
 
         let mut row_accum_error = 0.0;
-        self.next_errors = [0.0; 23];
+        self.next_errors = [0.0; LEDS];
 
-        for led in 0..23 {
+        for led in 0..LEDS {
             let corrected_intensity = intensity + row_accum_error + self.prev_errors[led];
 
             let render_amount = if corrected_intensity > 0.66 {
@@ -61,17 +62,12 @@ impl<const LEDS: usize> Mode<LEDS> for Dither {
                 self.next_errors[led - 1] += lower_accum_error;
             }
             self.next_errors[led] += mid_accum_error;
-            if led < 22 {
+            if led < LEDS - 1 {
                 self.next_errors[led + 1] += higher_accum_error;
             }
 
-            const GAMMA: f32 = 2.0;
-            let gamma_corrected_render_amount = render_amount.clamp(0.0, 1.0).powf(GAMMA);
-            let colour = (
-                (255.0 * gamma_corrected_render_amount) as u8,
-                (255.0 * gamma_corrected_render_amount) as u8,
-                0,
-            );
+            let level = (255.0 * render_amount) as u8;
+            let colour = (level, level, 0);
             self.rgb[led] = colour;
         }
 
@@ -84,10 +80,10 @@ impl<const LEDS: usize> Mode<LEDS> for Dither {
     }
 }
 
-pub fn create_dither<const LEDS: usize>() -> Box<dyn Mode<LEDS>> {
+pub fn create_dither<const LEDS: usize>(_rng: &mut StdRng) -> Box<dyn Mode<LEDS>> {
     Box::new(Dither {
-        prev_errors: [0.0; 23],
-        next_errors: [0.0; 23],
-        rgb: [(0, 0, 0); 23],
+        prev_errors: [0.0; LEDS],
+        next_errors: [0.0; LEDS],
+        rgb: [(0, 0, 0); LEDS],
     })
 }
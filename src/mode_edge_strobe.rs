@@ -1,5 +1,6 @@
 use crate::leds;
 use crate::structs::{FrameState, Mode};
+use rand::rngs::StdRng;
 use std::io;
 
 struct EdgeStrobe {
@@ -30,6 +31,6 @@ impl<const LEDS: usize> Mode<LEDS> for EdgeStrobe {
     }
 }
 
-pub fn construct_edge_strobe<const LEDS: usize>() -> Box<dyn Mode<LEDS>> {
+pub fn construct_edge_strobe<const LEDS: usize>(_rng: &mut StdRng) -> Box<dyn Mode<LEDS>> {
     Box::new(EdgeStrobe { last_spin_pos: 0.0 })
 }
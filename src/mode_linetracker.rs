@@ -1,5 +1,7 @@
+use crate::helpers::led_at;
 use crate::leds;
 use crate::structs::{FrameState, Mode};
+use rand::rngs::StdRng;
 use std::io;
 
 struct LineTracker {
@@ -38,9 +40,9 @@ fn spiral_out<const LEDS: usize>(frame: &FrameState) -> usize {
     (frame.spin_pos * (LEDS as f32)).clamp(0.0, (LEDS - 1) as f32) as usize
 }
 
-pub fn construct_spiral_out<const LEDS: usize>() -> Box<dyn Mode<LEDS>> {
+pub fn construct_spiral_out<const LEDS: usize>(_rng: &mut StdRng) -> Box<dyn Mode<LEDS>> {
     Box::new(LineTracker {
-        led: 11,
+        led: led_at(0.48, LEDS),
         func: spiral_out::<LEDS>,
     })
 }
@@ -48,15 +50,15 @@ pub fn construct_spiral_out<const LEDS: usize>() -> Box<dyn Mode<LEDS>> {
 fn squarewave_flower<const LEDS: usize>(frame: &FrameState) -> usize {
     let phase = (frame.spin_pos * 3.0) % 1.0;
     if phase > 0.5 {
-        LEDS
+        LEDS - 1
     } else {
         0
     }
 }
 
-pub fn construct_squarewave_flower<const LEDS: usize>() -> Box<dyn Mode<LEDS>> {
+pub fn construct_squarewave_flower<const LEDS: usize>(_rng: &mut StdRng) -> Box<dyn Mode<LEDS>> {
     Box::new(LineTracker {
-        led: 11,
+        led: led_at(0.48, LEDS),
         func: squarewave_flower::<LEDS>,
     })
 }
@@ -64,15 +66,15 @@ pub fn construct_squarewave_flower<const LEDS: usize>() -> Box<dyn Mode<LEDS>> {
 fn squarewave<const LEDS: usize>(frame: &FrameState) -> usize {
     let phase = (frame.spin_pos * 3.0) % 1.0;
     if phase > 0.5 {
-        LEDS
+        LEDS - 1
     } else {
         LEDS * 2 / 3
     }
 }
 
-pub fn construct_squarewave<const LEDS: usize>() -> Box<dyn Mode<LEDS>> {
+pub fn construct_squarewave<const LEDS: usize>(_rng: &mut StdRng) -> Box<dyn Mode<LEDS>> {
     Box::new(LineTracker {
-        led: 11,
+        led: led_at(0.48, LEDS),
         func: squarewave::<LEDS>,
     })
 }
@@ -1,4 +1,6 @@
-use crate::helpers::{blank_leds, fraction_to_rgb, spinpos_to_rgb};
+use crate::helpers::{
+    blank_leds, fraction_to_rgb, led_at, leds_between, set_from_rim, spinpos_to_rgb,
+};
 use crate::leds::Side;
 use crate::structs::{FrameState, RGB24};
 use rand::Rng;
@@ -7,44 +9,33 @@ use std::f32::consts::TAU;
 use std::io;
 
 /// This renders the first side of the wheel with:
-///  * an 8 pixel rainbow around the wheel
+///  * a rainbow band around the middle of the wheel
 ///  * a constant blue LED
-///  * green and purple LEDs that tick once per frame
+///  * green and purple LEDs in the outer four that tick once per frame
 ///    to show the size of a rotational-pixel
 pub fn render_rainbows(wheel_leds: &mut [RGB24], framestate: &FrameState) -> io::Result<()> {
-    for led in 0..8 {
-        wheel_leds[led] = (0, 0, 0);
-    }
+    let leds = wheel_leds.len();
+    blank_leds(wheel_leds);
 
     let rainbow_colour = spinpos_to_rgb(framestate);
 
-    for led in 8..16 {
+    for led in leds_between(0.35, 0.7, leds) {
         wheel_leds[led] = rainbow_colour;
     }
 
-    wheel_leds[16] = (0, 0, 0);
-
-    wheel_leds[17] = (0, 0, 255);
-
-    wheel_leds[18] = (0, 0, 0);
+    // the outer six LEDs, rim first, as far as the arm reaches
+    let mut rim = [(0, 0, 0); 6];
+    rim[5] = (0, 0, 255);
 
     let counter_phase = framestate.loop_counter % 6;
     if counter_phase == 0 {
-        wheel_leds[19] = (0, 0, 0);
-        wheel_leds[20] = (0, 255, 0);
-        wheel_leds[21] = (0, 0, 0);
-        wheel_leds[22] = (0, 64, 0);
+        rim[2] = (0, 255, 0);
+        rim[0] = (0, 64, 0);
     } else if counter_phase == 3 {
-        wheel_leds[19] = (32, 0, 32);
-        wheel_leds[20] = (0, 0, 0);
-        wheel_leds[21] = (128, 0, 128);
-        wheel_leds[22] = (0, 0, 0);
-    } else {
-        wheel_leds[19] = (0, 0, 0);
-        wheel_leds[20] = (0, 0, 0);
-        wheel_leds[21] = (0, 0, 0);
-        wheel_leds[22] = (0, 0, 0);
+        rim[3] = (32, 0, 32);
+        rim[1] = (128, 0, 128);
     }
+    set_from_rim(wheel_leds, &rim);
 
     Ok(())
 }
@@ -55,9 +46,7 @@ pub fn render_rainbow_rgb_rim(wheel_leds: &mut [RGB24], framestate: &FrameState)
     blank_leds(wheel_leds);
 
     let (r, g, b) = spinpos_to_rgb(framestate);
-    wheel_leds[20] = (r, 0, 0);
-    wheel_leds[21] = (0, g, 0);
-    wheel_leds[22] = (0, 0, b);
+    set_from_rim(wheel_leds, &[(0, 0, b), (0, g, 0), (r, 0, 0)]);
 
     Ok(())
 }
@@ -69,15 +58,15 @@ pub fn render_rainbow_rgb_speckle_rim(
     blank_leds(wheel_leds);
 
     let (r, g, b) = spinpos_to_rgb(framestate);
-    wheel_leds[18] = (r, g, b);
 
+    // the outer five LEDs, rim first
+    let mut rim = [(r, g, b), (0, 0, 0), (0, 0, 0), (0, 0, 0), (r, g, b)];
     match framestate.loop_counter % 3 {
-        0 => wheel_leds[19] = (r, 0, 0),
-        1 => wheel_leds[20] = (0, g, 0),
-        _ => wheel_leds[21] = (0, 0, b),
+        0 => rim[3] = (r, 0, 0),
+        1 => rim[2] = (0, g, 0),
+        _ => rim[1] = (0, 0, b),
     };
-
-    wheel_leds[22] = (r, g, b);
+    set_from_rim(wheel_leds, &rim);
 
     Ok(())
 }
@@ -92,26 +81,33 @@ pub fn render_rainbow_rgb_plus_rim(
     blank_leds(wheel_leds);
 
     let (r, g, b) = spinpos_to_rgb(framestate);
-    wheel_leds[17] = (r, 0, b);
-    wheel_leds[18] = (r, 0, 0);
-    wheel_leds[19] = (r, g, 0);
-    wheel_leds[20] = (0, g, 0);
-    wheel_leds[21] = (0, g, b);
-    wheel_leds[22] = (0, 0, b);
+    set_from_rim(
+        wheel_leds,
+        &[
+            (0, 0, b),
+            (0, g, b),
+            (0, g, 0),
+            (r, g, 0),
+            (r, 0, 0),
+            (r, 0, b),
+        ],
+    );
 
     Ok(())
 }
 
 /// This renders the first side of the wheel with
-/// an 8 pixel rainbow around the rim of wheel
+/// a rainbow around the outer third of the wheel
 pub fn render_rainbow_rim(wheel_leds: &mut [RGB24], framestate: &FrameState) -> io::Result<()> {
-    for led in 0..15 {
+    let leds = wheel_leds.len();
+
+    for led in leds_between(0.0, 0.65, leds) {
         wheel_leds[led] = (0, 0, 0);
     }
 
     let rainbow_colour = spinpos_to_rgb(framestate);
 
-    for led in 15..23 {
+    for led in leds_between(0.65, 1.0, leds) {
         wheel_leds[led] = rainbow_colour;
     }
 
@@ -122,7 +118,9 @@ pub fn render_rainbow_rim_sine(
     wheel_leds: &mut [RGB24],
     framestate: &FrameState,
 ) -> io::Result<()> {
-    for led in 0..15 {
+    let leds = wheel_leds.len();
+
+    for led in leds_between(0.0, 0.65, leds) {
         wheel_leds[led] = (0, 0, 0);
     }
 
@@ -130,9 +128,7 @@ pub fn render_rainbow_rim_sine(
     let g = ((0.5 + 0.5 * (15.0 * framestate.spin_pos * TAU).sin()).powf(2.0) * 255.0) as u8;
     let b = ((0.5 + 0.5 * (3.0 * framestate.spin_pos * TAU).sin()).powf(2.0) * 255.0) as u8;
 
-    wheel_leds[22] = (r, 0, 0);
-    wheel_leds[21] = (0, g, 0);
-    wheel_leds[20] = (0, 0, b);
+    set_from_rim(wheel_leds, &[(r, 0, 0), (0, g, 0), (0, 0, b)]);
 
     Ok(())
 }
@@ -141,7 +137,9 @@ pub fn render_rainbow_rim_sine_overlay(
     wheel_leds: &mut [RGB24],
     framestate: &FrameState,
 ) -> io::Result<()> {
-    for led in 0..15 {
+    let leds = wheel_leds.len();
+
+    for led in leds_between(0.0, 0.65, leds) {
         wheel_leds[led] = (0, 0, 0);
     }
 
@@ -149,13 +147,18 @@ pub fn render_rainbow_rim_sine_overlay(
     let g = ((0.5 + 0.5 * (15.0 * framestate.spin_pos * TAU).sin()).powf(2.0) * 255.0) as u8;
     let b = ((0.5 + 0.5 * (3.0 * framestate.spin_pos * TAU).sin()).powf(2.0) * 255.0) as u8;
 
-    wheel_leds[22] = (r, g, 0);
-    wheel_leds[21] = (0, g, b);
-    wheel_leds[20] = (r, 0, b);
-    wheel_leds[19] = (r, g, 0);
-    wheel_leds[18] = (0, g, b);
-    wheel_leds[17] = (r, 0, b);
-    wheel_leds[16] = (r, g, b);
+    set_from_rim(
+        wheel_leds,
+        &[
+            (r, g, 0),
+            (0, g, b),
+            (r, 0, b),
+            (r, g, 0),
+            (0, g, b),
+            (r, 0, b),
+            (r, g, b),
+        ],
+    );
 
     Ok(())
 }
@@ -164,7 +167,10 @@ pub fn render_rainbow_rim_spaced(
     wheel_leds: &mut [RGB24],
     framestate: &FrameState,
 ) -> io::Result<()> {
-    for led in 0..15 {
+    let leds = wheel_leds.len();
+    let rim = leds - 1;
+
+    for led in leds_between(0.0, 0.65, leds) {
         wheel_leds[led] = (0, 0, 0);
     }
 
@@ -172,11 +178,11 @@ pub fn render_rainbow_rim_spaced(
     let g = ((0.5 + 0.5 * (15.0 * framestate.spin_pos * TAU).sin()).powf(2.0) * 255.0) as u8;
     let b = ((0.5 + 0.5 * (3.0 * framestate.spin_pos * TAU).sin()).powf(2.0) * 255.0) as u8;
 
-    wheel_leds[22] = (r, 0, 0);
-    wheel_leds[20] = (r, 0, 0);
-    wheel_leds[17] = (0, g, 0);
-    wheel_leds[12] = (0, g, 0);
-    wheel_leds[4] = (0, 0, b);
+    wheel_leds[rim] = (r, 0, 0);
+    wheel_leds[led_at(0.87, leds)] = (r, 0, 0);
+    wheel_leds[led_at(0.74, leds)] = (0, g, 0);
+    wheel_leds[led_at(0.52, leds)] = (0, g, 0);
+    wheel_leds[led_at(0.17, leds)] = (0, 0, b);
     wheel_leds[0] = (0, 0, b);
 
     Ok(())
@@ -186,7 +192,10 @@ pub fn render_rainbow_rim_spaced2(
     wheel_leds: &mut [RGB24],
     framestate: &FrameState,
 ) -> io::Result<()> {
-    for led in 0..15 {
+    let leds = wheel_leds.len();
+    let rim = leds - 1;
+
+    for led in leds_between(0.0, 0.65, leds) {
         wheel_leds[led] = (0, 0, 0);
     }
 
@@ -197,31 +206,37 @@ pub fn render_rainbow_rim_spaced2(
     let b = ((0.5 + 0.5 * (3.0 * framestate.spin_pos * TAU).sin()).powf(2.0) * 255.0) as u8;
     let b2 = ((0.5 + 0.5 * (4.0 * framestate.spin_pos * TAU).sin()).powf(2.0) * 255.0) as u8;
 
-    wheel_leds[22] = (r, 0, 0);
-    wheel_leds[21] = (r2, 0, 0);
-    wheel_leds[18] = (0, g, 0);
-    wheel_leds[17] = (0, g2, 0);
-    wheel_leds[14] = (0, 0, b);
-    wheel_leds[13] = (0, 0, b2);
+    let g_led = led_at(0.78, leds);
+    let b_led = led_at(0.61, leds);
+
+    // on short arms the pairs overlap, and the outer LED of each wins
+    wheel_leds[b_led.saturating_sub(1)] = (0, 0, b2);
+    wheel_leds[b_led] = (0, 0, b);
+    wheel_leds[g_led.saturating_sub(1)] = (0, g2, 0);
+    wheel_leds[g_led] = (0, g, 0);
+    wheel_leds[rim.saturating_sub(1)] = (r2, 0, 0);
+    wheel_leds[rim] = (r, 0, 0);
 
     Ok(())
 }
 
-pub fn render_random_rim(wheel_leds: &mut [RGB24], _framestate: &FrameState) -> io::Result<()> {
-    for led in 0..20 {
+pub fn render_random_rim(wheel_leds: &mut [RGB24], framestate: &FrameState) -> io::Result<()> {
+    let rim_start = wheel_leds.len().saturating_sub(3);
+
+    for led in 0..rim_start {
         wheel_leds[led] = (0, 0, 0);
     }
 
     // starting at 1 avoids having all three bits off
     // (the 0 position) so there will always at least
     // be one LED on in each frame
-    let n = rand::thread_rng().gen_range(1, 8);
+    let n = framestate.rng().gen_range(1, 8);
 
-    for led in 0..3 {
+    for led in 0..wheel_leds.len() - rim_start {
         if n & (1 << led) != 0 {
-            wheel_leds[20 + led] = (255, 0, 0);
+            wheel_leds[rim_start + led] = (255, 0, 0);
         } else {
-            wheel_leds[20 + led] = (0, 0, 0);
+            wheel_leds[rim_start + led] = (0, 0, 0);
         }
     }
 
@@ -230,23 +245,25 @@ pub fn render_random_rim(wheel_leds: &mut [RGB24], _framestate: &FrameState) ->
 
 pub fn render_random_rim_red_yellow(
     wheel_leds: &mut [RGB24],
-    _framestate: &FrameState,
+    framestate: &FrameState,
 ) -> io::Result<()> {
-    for led in 0..20 {
+    let rim_start = wheel_leds.len().saturating_sub(3);
+
+    for led in 0..rim_start {
         wheel_leds[led] = (0, 0, 0);
     }
 
     // starting at 1 avoids having all three bits off
     // (the 0 position) so there will always at least
     // be one LED on in each frame
-    let n = rand::thread_rng().gen_range(1, 8);
+    let n = framestate.rng().gen_range(1, 8);
 
-    for led in 0..3 {
+    for led in 0..wheel_leds.len() - rim_start {
         if n & (1 << led) != 0 {
-            let yellow_amount = (2.0_f32).powf(rand::thread_rng().gen_range(0.0, 7.5)) as u8;
-            wheel_leds[20 + led] = (255, yellow_amount, 0);
+            let yellow_amount = (2.0_f32).powf(framestate.rng().gen_range(0.0, 7.5)) as u8;
+            wheel_leds[rim_start + led] = (255, yellow_amount, 0);
         } else {
-            wheel_leds[20 + led] = (0, 0, 0);
+            wheel_leds[rim_start + led] = (0, 0, 0);
         }
     }
 
@@ -268,22 +285,24 @@ pub fn render_spin_rim<const LEDS: usize>(
     let k = (framestate.spin_pos * 3.0 + t) % 1.0;
 
     if k < 0.33 {
-        wheel_leds[22] = (255, 64, 0);
-        wheel_leds[21] = (255, 128, 0);
-        wheel_leds[20] = (255, 64, 0);
+        set_from_rim(wheel_leds, &[(255, 64, 0), (255, 128, 0), (255, 64, 0)]);
     }
 
     Ok(())
 }
 
 pub fn render_pulsed_rainbow(wheel_leds: &mut [RGB24], framestate: &FrameState) -> io::Result<()> {
-    for led in 0..15 {
+    let leds = wheel_leds.len();
+
+    for led in leds_between(0.0, 0.65, leds) {
         wheel_leds[led] = (0, 0, 0);
     }
 
-    for led in 15..23 {
-        let led_n = led - 15;
-        let frac: f32 = ((led - 15) as f32) / 8.0;
+    let band = leds_between(0.65, 1.0, leds);
+    let band_len = band.len();
+    for led in band.clone() {
+        let led_n = led - band.start;
+        let frac: f32 = (led_n as f32) / (band_len as f32);
         let v1 = (framestate.spin_pos + frac) % 1.0;
         let v2 = (v1 * (led_n as f32 + 2.0)) % 1.0;
         let v3 = if v2 > 0.5 { 1.0 } else { 0.0 };
@@ -298,22 +317,26 @@ pub fn render_pulsed_rainbow(wheel_leds: &mut [RGB24], framestate: &FrameState)
 ///  * a green time-based line
 ///  * a magenta spin position line
 pub fn render_sliders(wheel_leds: &mut [RGB24], framestate: &FrameState) -> io::Result<()> {
+    let leds = wheel_leds.len();
     let now_millis = framestate.now.as_millis();
 
-    // this should range from 0..23 over the period of 1 second, which is
-    // around the right time for one wheel spin
-    let back_led: usize = ((now_millis % 1000) * 23 / 1000) as usize;
+    // this should range over all the LEDs over the period of 1 second,
+    // which is around the right time for one wheel spin
+    let back_led: usize = ((now_millis % 1000) * leds as u128 / 1000) as usize;
+
+    let spin_back_led: usize = (framestate.spin_pos * leds as f32) as usize;
 
-    let spin_back_led: usize = (framestate.spin_pos * 23.0) as usize;
+    // the magenta line repeats a third of the way along
+    let third = (leds as f32 / 3.0).round() as usize;
 
-    for l in 0..23 {
+    for l in 0..leds {
         let g = if l == back_led { 255 } else { 0 };
 
         let r = if l == spin_back_led {
             255
-        } else if l == (spin_back_led + 8) % 23 {
+        } else if l == (spin_back_led + third) % leds {
             255
-        } else if l == (spin_back_led + 16) % 23 {
+        } else if l == (spin_back_led + third * 2) % leds {
             255
         } else {
             0
@@ -327,11 +350,12 @@ pub fn render_sliders(wheel_leds: &mut [RGB24], framestate: &FrameState) -> io::
 /// This renders three slices with black between them, each slice being one
 /// of red, green or blue
 pub fn render_rgb_trio(wheel_leds: &mut [RGB24], framestate: &FrameState) -> io::Result<()> {
-    for led in 0..23 {
+    let leds = wheel_leds.len();
+    for led in 0..leds {
         // led 0 should be dimmest
-        // led 22 the brightest
+        // the rim led the brightest
         // this will exponentially scale up to 128 max
-        let brightness = 1 << (led / 3);
+        let brightness = 1 << (led * 8 / leds);
         let colour: RGB24;
 
         if framestate.spin_pos < 0.16 {
@@ -359,10 +383,15 @@ pub fn render_rgb_trio(wheel_leds: &mut [RGB24], framestate: &FrameState) -> io:
 pub fn render_centre_red(wheel_leds: &mut [RGB24], _framestate: &FrameState) -> io::Result<()> {
     blank_leds(wheel_leds);
 
-    for n in 0..8 {
+    let leds = wheel_leds.len();
+    let centre = led_at(0.48, leds);
+
+    for n in 0..cmp::min(8, centre + 1) {
         let colour = (1 << (7 - n), 0, 0);
-        wheel_leds[12 + n] = colour;
-        wheel_leds[11 - n] = colour;
+        if centre + 1 + n < leds {
+            wheel_leds[centre + 1 + n] = colour;
+        }
+        wheel_leds[centre - n] = colour;
     }
 
     Ok(())
@@ -373,14 +402,16 @@ pub fn render_centre_red(wheel_leds: &mut [RGB24], _framestate: &FrameState) ->
 pub fn render_fib_concentric(wheel_leds: &mut [RGB24], _framestate: &FrameState) -> io::Result<()> {
     blank_leds(wheel_leds);
 
+    let leds = wheel_leds.len();
     let amber = (128, 16, 0);
-    wheel_leds[23 - 1] = amber;
-    wheel_leds[23 - 2] = amber;
-    wheel_leds[23 - 3] = amber;
-    wheel_leds[23 - 5] = amber;
-    wheel_leds[23 - 8] = amber;
-    wheel_leds[23 - 13] = amber;
-    wheel_leds[23 - 21] = amber;
+
+    // rings at fibonacci distances in from the rim
+    let (mut a, mut b) = (1, 2);
+    wheel_leds[leds - a] = amber;
+    while b <= leds {
+        wheel_leds[leds - b] = amber;
+        (a, b) = (b, a + b);
+    }
 
     Ok(())
 }
@@ -388,11 +419,10 @@ pub fn render_fib_concentric(wheel_leds: &mut [RGB24], _framestate: &FrameState)
 pub fn render_sine(wheel_leds: &mut [RGB24], framestate: &FrameState) -> io::Result<()> {
     blank_leds(wheel_leds);
 
+    let leds = wheel_leds.len();
     let phase = (framestate.spin_pos * TAU * 10.0).sin();
 
-    // beware of casting to unsigned when there could still be
-    // negatives around
-    let led = (17.0 + phase * 5.0) as usize;
+    let led = led_at(0.74 + phase * 0.22, leds);
 
     wheel_leds[led] = (0, 255, 0);
 
@@ -402,14 +432,13 @@ pub fn render_sine(wheel_leds: &mut [RGB24], framestate: &FrameState) -> io::Res
 pub fn render_helix(wheel_leds: &mut [RGB24], framestate: &FrameState) -> io::Result<()> {
     blank_leds(wheel_leds);
 
+    let leds = wheel_leds.len();
     let phase = (framestate.spin_pos * TAU * 10.0).sin();
 
-    // beware of casting to unsigned when there could still be
-    // negatives around
-    let led = cmp::min((17.0 + phase * 6.0) as usize, 22);
+    let led = led_at(0.74 + phase * 0.26, leds);
     wheel_leds[led] = (64, 0, 64);
 
-    let led = cmp::min((17.0 - phase * 6.0) as usize, 22);
+    let led = led_at(0.74 - phase * 0.26, leds);
     wheel_leds[led] = (0, 255, 0);
 
     Ok(())
@@ -418,20 +447,19 @@ pub fn render_helix(wheel_leds: &mut [RGB24], framestate: &FrameState) -> io::Re
 pub fn render_sine_full(wheel_leds: &mut [RGB24], framestate: &FrameState) -> io::Result<()> {
     blank_leds(wheel_leds);
 
+    let leds = wheel_leds.len();
     let phase = (framestate.spin_pos * TAU * 10.0).sin();
 
-    // beware of casting to unsigned when there could still be
-    // negatives around
-    let led = (17.0 + phase * 5.0) as usize;
+    let led = led_at(0.74 + phase * 0.22, leds);
 
     wheel_leds[led] = (0, 255, 0);
 
     let phase2 = (framestate.spin_pos * TAU * 7.0).sin();
-    let led2 = (8.0 + phase2 * 3.0) as usize;
+    let led2 = led_at(0.35 + phase2 * 0.13, leds);
     wheel_leds[led2] = (255, 0, 0);
 
     let phase3 = (framestate.spin_pos * TAU * 3.0).sin();
-    let led3 = (3.0 + phase3 * 2.0) as usize;
+    let led3 = led_at(0.13 + phase3 * 0.09, leds);
     wheel_leds[led3] = (0, 0, 255);
 
     Ok(())
@@ -444,24 +472,18 @@ pub fn render_graycode_rim(wheel_leds: &mut [RGB24], framestate: &FrameState) ->
 
     let gray = segment ^ (segment >> 1);
 
+    let leds = wheel_leds.len();
     let amber = (255, 32, 0);
 
-    if (gray & 0b001) != 0 {
-        wheel_leds[22] = amber;
-        wheel_leds[21] = amber;
-        wheel_leds[20] = amber;
-    }
-
-    if (gray & 0b010) != 0 {
-        wheel_leds[19] = amber;
-        wheel_leds[18] = amber;
-        wheel_leds[17] = amber;
-    }
+    // one band of LEDs for each bit, least significant at the rim
+    let bands = [(0.87, 1.0), (0.74, 0.87), (0.61, 0.74)];
 
-    if (gray & 0b100) != 0 {
-        wheel_leds[16] = amber;
-        wheel_leds[15] = amber;
-        wheel_leds[14] = amber;
+    for (bit, (from, to)) in bands.iter().enumerate() {
+        if (gray & (1 << bit)) != 0 {
+            for led in leds_between(*from, *to, leds) {
+                wheel_leds[led] = amber;
+            }
+        }
     }
 
     Ok(())
@@ -473,7 +495,7 @@ pub fn render_radial_stripes(wheel_leds: &mut [RGB24], framestate: &FrameState)
     let segment = (framestate.spin_pos * 32.0) as u8; // could go over 32 because spinpos can go over 1
 
     if segment % 2 == 1 {
-        for led in 12..23 {
+        for led in leds_between(0.52, 1.0, wheel_leds.len()) {
             wheel_leds[led] = (64, 64, 64);
         }
     }
@@ -482,17 +504,19 @@ pub fn render_radial_stripes(wheel_leds: &mut [RGB24], framestate: &FrameState)
 }
 
 pub fn render_europa(wheel_leds: &mut [RGB24], framestate: &FrameState) -> io::Result<()> {
+    let leds = wheel_leds.len();
+
     // establish a blue canvas
-    for led in 0..23 {
+    for led in 0..leds {
         wheel_leds[led] = (0, 0, 32);
     }
 
     let segment = (framestate.spin_pos * 12.0) % 1.0; // could go over 12 because spinpos can go over 1
 
     if segment < 0.08 || (segment >= 0.16 && segment < 0.24) {
-        wheel_leds[18] = (255, 255, 0);
+        wheel_leds[led_at(0.78, leds)] = (255, 255, 0);
     } else if segment < 0.16 {
-        for led in 17..20 {
+        for led in leds_between(0.74, 0.87, leds) {
             wheel_leds[led] = (255, 255, 0);
         }
     }
@@ -503,10 +527,14 @@ pub fn render_europa(wheel_leds: &mut [RGB24], framestate: &FrameState) -> io::R
 pub fn render_fade_spirals(wheel_leds: &mut [RGB24], framestate: &FrameState) -> io::Result<()> {
     // establish a blank canvas
 
-    let s1 = cmp::min(22, (23.0 * framestate.spin_pos) as i8);
-    let s2 = cmp::min(22, (23.0 * ((framestate.spin_pos + 0.5) % 1.0)) as i8);
+    let leds = wheel_leds.len();
+    let s1 = cmp::min(leds - 1, (leds as f32 * framestate.spin_pos) as usize) as i32;
+    let s2 = cmp::min(
+        leds - 1,
+        (leds as f32 * ((framestate.spin_pos + 0.5) % 1.0)) as usize,
+    ) as i32;
 
-    for led in 0..23 {
+    for led in 0..leds as i32 {
         let dist_s1 = (s1 - led).abs() as u8;
         let dist_s2 = (s2 - led).abs() as u8;
         if dist_s1 < dist_s2 {
@@ -530,17 +558,18 @@ pub fn render_fade_spirals(wheel_leds: &mut [RGB24], framestate: &FrameState) ->
 pub fn render_fade_quarters(wheel_leds: &mut [RGB24], framestate: &FrameState) -> io::Result<()> {
     let fade_frac = (framestate.spin_pos * 4.0) % 1.0;
 
-    // some gamma correction
-    let brightness = fade_frac.powf(3.0).min(1.0);
+    let brightness = fade_frac.min(1.0);
 
     let pix_brightness_red = (255.0 * brightness) as u8;
     let pix_brightness_green = (64.0 * brightness) as u8;
 
-    for led in 0..11 {
-        wheel_leds[led as usize] = (0, 0, 0);
+    let leds = wheel_leds.len();
+
+    for led in leds_between(0.0, 0.48, leds) {
+        wheel_leds[led] = (0, 0, 0);
     }
-    for led in 11..23 {
-        wheel_leds[led as usize] = (pix_brightness_red, pix_brightness_green, 0);
+    for led in leds_between(0.48, 1.0, leds) {
+        wheel_leds[led] = (pix_brightness_red, pix_brightness_green, 0);
     }
 
     Ok(())
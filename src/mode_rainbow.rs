@@ -1,21 +1,22 @@
 use crate::helpers::fraction_to_rgb;
 use crate::leds;
 use crate::structs::{FrameState, Mode};
+use rand::rngs::StdRng;
 use rand::Rng;
 use std::io;
 
-struct RainbowOnOff {
-    colours: [(bool, f32); 23],
+struct RainbowOnOff<const LEDS: usize> {
+    colours: [(bool, f32); LEDS],
 }
 
-impl<const LEDS: usize> Mode<LEDS> for RainbowOnOff {
+impl<const LEDS: usize> Mode<LEDS> for RainbowOnOff<LEDS> {
     fn render(
         &self,
         side: leds::Side,
         leds: &mut leds::WheelLEDs<LEDS>,
         _frame: &FrameState,
     ) -> io::Result<()> {
-        for led in 0..23 {
+        for led in 0..LEDS {
             let (active, hue) = self.colours[led];
             if active {
                 leds.set(side, led, fraction_to_rgb(hue, None));
@@ -27,10 +28,10 @@ impl<const LEDS: usize> Mode<LEDS> for RainbowOnOff {
         Ok(())
     }
 
-    fn step(&mut self, _frame: &FrameState) -> io::Result<()> {
-        let led = rand::thread_rng().gen_range(0, 23);
+    fn step(&mut self, frame: &FrameState) -> io::Result<()> {
+        let led = frame.rng().gen_range(0, LEDS);
 
-        let action = rand::thread_rng().gen_range(0, 5);
+        let action = frame.rng().gen_range(0, 5);
 
         if action == 0 {
             self.colours[led] = (false, 0.0);
@@ -38,7 +39,7 @@ impl<const LEDS: usize> Mode<LEDS> for RainbowOnOff {
             let hue = if led > 0 && fst(self.colours[led - 1]) {
                 snd(self.colours[led - 1])
             } else {
-                rand::thread_rng().gen_range(0.0, 1.0)
+                frame.rng().gen_range(0.0, 1.0)
             };
 
             self.colours[led] = (true, hue);
@@ -55,8 +56,8 @@ fn snd((_, b): (bool, f32)) -> f32 {
     b
 }
 
-pub fn construct_rainbow_on_off<const LEDS: usize>() -> Box<dyn Mode<LEDS>> {
+pub fn construct_rainbow_on_off<const LEDS: usize>(_rng: &mut StdRng) -> Box<dyn Mode<LEDS>> {
     Box::new(RainbowOnOff {
-        colours: [(false, 0.0); 23],
+        colours: [(false, 0.0); LEDS],
     })
 }
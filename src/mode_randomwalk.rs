@@ -1,6 +1,7 @@
-use crate::helpers::fraction_to_rgb;
+use crate::helpers::{fraction_to_rgb, led_at};
 use crate::leds;
 use crate::structs::{FrameState, Mode};
+use rand::rngs::StdRng;
 use rand::Rng;
 use std::io;
 
@@ -15,17 +16,17 @@ impl<const LEDS: usize> Mode<LEDS> for RandomWalkDot {
         leds: &mut leds::WheelLEDs<LEDS>,
         _frame: &FrameState,
     ) -> io::Result<()> {
-        for led in 0..23 {
+        for led in 0..LEDS {
             leds.set(side, led, (0, 0, 0));
         }
         leds.set(side, self.led, (255, 8, 0));
         Ok(())
     }
 
-    fn step(&mut self, _frame: &FrameState) -> io::Result<()> {
-        let choice = rand::thread_rng().gen_range(0, 3);
+    fn step(&mut self, frame: &FrameState) -> io::Result<()> {
+        let choice = frame.rng().gen_range(0, 3);
 
-        if choice == 1 && self.led < 22 {
+        if choice == 1 && self.led < LEDS - 1 {
             self.led += 1;
         } else if choice == 2 && self.led > 0 {
             self.led -= 1;
@@ -35,8 +36,10 @@ impl<const LEDS: usize> Mode<LEDS> for RandomWalkDot {
     }
 }
 
-pub fn create_random_walk_dot<const LEDS: usize>() -> Box<dyn Mode<LEDS>> {
-    Box::new(RandomWalkDot { led: 11 })
+pub fn create_random_walk_dot<const LEDS: usize>(_rng: &mut StdRng) -> Box<dyn Mode<LEDS>> {
+    Box::new(RandomWalkDot {
+        led: led_at(0.48, LEDS),
+    })
 }
 
 struct Lightning {
@@ -51,49 +54,52 @@ impl<const LEDS: usize> Mode<LEDS> for Lightning {
         leds: &mut leds::WheelLEDs<LEDS>,
         _frame: &FrameState,
     ) -> io::Result<()> {
-        for led in 0..23 {
+        for led in 0..LEDS {
             leds.set(side, led, (0, 0, 0));
         }
         leds.set(side, self.led, fraction_to_rgb(self.hue, None));
         Ok(())
     }
 
-    fn step(&mut self, _frame: &FrameState) -> io::Result<()> {
-        let choice = rand::thread_rng().gen_range(0, 3);
+    fn step(&mut self, frame: &FrameState) -> io::Result<()> {
+        let choice = frame.rng().gen_range(0, 3);
 
-        if choice == 1 && self.led < 22 {
+        if choice == 1 && self.led < LEDS - 1 {
             self.led += 1;
         } else if choice == 2 && self.led > 0 {
             self.led -= 1;
-        } else if choice == 1 && self.led >= 22 {
-            self.led = 11;
-            self.hue = rand::thread_rng().gen_range(0.0, 1.0);
+        } else if choice == 1 && self.led >= LEDS - 1 {
+            self.led = led_at(0.48, LEDS);
+            self.hue = frame.rng().gen_range(0.0, 1.0);
         } else if choice == 2 && self.led <= 0 {
-            self.led = 11;
-            self.hue = rand::thread_rng().gen_range(0.0, 1.0);
+            self.led = led_at(0.48, LEDS);
+            self.hue = frame.rng().gen_range(0.0, 1.0);
         }
 
         Ok(())
     }
 }
 
-pub fn create_lightning<const LEDS: usize>() -> Box<dyn Mode<LEDS>> {
-    Box::new(Lightning { led: 11, hue: 0.0 })
+pub fn create_lightning<const LEDS: usize>(_rng: &mut StdRng) -> Box<dyn Mode<LEDS>> {
+    Box::new(Lightning {
+        led: led_at(0.48, LEDS),
+        hue: 0.0,
+    })
 }
 
-struct ForkLightning {
-    leds: [bool; 23],
+struct ForkLightning<const LEDS: usize> {
+    leds: [bool; LEDS],
     hue: f32,
 }
 
-impl<const LEDS: usize> Mode<LEDS> for ForkLightning {
+impl<const LEDS: usize> Mode<LEDS> for ForkLightning<LEDS> {
     fn render(
         &self,
         side: leds::Side,
         leds: &mut leds::WheelLEDs<LEDS>,
         _frame: &FrameState,
     ) -> io::Result<()> {
-        for led in 0..23 {
+        for led in 0..LEDS {
             if self.leds[led] {
                 leds.set(side, led, fraction_to_rgb(self.hue, None));
             } else {
@@ -104,20 +110,20 @@ impl<const LEDS: usize> Mode<LEDS> for ForkLightning {
         Ok(())
     }
 
-    fn step(&mut self, _frame: &FrameState) -> io::Result<()> {
-        let mut newleds = [false; 23];
+    fn step(&mut self, frame: &FrameState) -> io::Result<()> {
+        let mut newleds = [false; LEDS];
 
-        for led in 0..23 {
+        for led in 0..LEDS {
             if self.leds[led] {
-                let choice: f32 = rand::thread_rng().gen_range(0.0, 3.33);
+                let choice: f32 = frame.rng().gen_range(0.0, 3.33);
 
-                if choice < 1.0 && led < 22 {
+                if choice < 1.0 && led < LEDS - 1 {
                     newleds[led + 1] = true;
                 } else if choice < 2.0 && led > 0 {
                     newleds[led - 1] = true;
                 } else if choice < 3.0 {
                     newleds[led] = true;
-                } else if choice < 3.3 && led < 22 && led > 0 {
+                } else if choice < 3.3 && led < LEDS - 1 && led > 0 {
                     // fork
                     // only fork if there is nothing else nearby in previous iteration, trying to keep density down
                     if !self.leds[led - 1] && !self.leds[led + 1] {
@@ -133,57 +139,57 @@ impl<const LEDS: usize> Mode<LEDS> for ForkLightning {
         self.leds = newleds;
 
         let mut alive = false;
-        for led in 0..23 {
+        for led in 0..LEDS {
             if self.leds[led] {
                 alive = true;
             }
         }
 
         if !alive {
-            self.leds[11] = true;
-            self.hue = rand::thread_rng().gen_range(0.0, 1.0);
+            self.leds[led_at(0.48, LEDS)] = true;
+            self.hue = frame.rng().gen_range(0.0, 1.0);
         }
 
         Ok(())
     }
 }
 
-pub fn create_fork_lightning<const LEDS: usize>() -> Box<dyn Mode<LEDS>> {
+pub fn create_fork_lightning<const LEDS: usize>(_rng: &mut StdRng) -> Box<dyn Mode<LEDS>> {
     Box::new(ForkLightning {
-        leds: [false; 23],
+        leds: [false; LEDS],
         hue: 0.0,
     })
 }
 
-struct FloatSpray {
-    leds: [f32; 23],
+struct FloatSpray<const LEDS: usize> {
+    leds: [f32; LEDS],
 }
 
-impl<const LEDS: usize> Mode<LEDS> for FloatSpray {
+impl<const LEDS: usize> Mode<LEDS> for FloatSpray<LEDS> {
     fn render(
         &self,
         side: leds::Side,
         leds: &mut leds::WheelLEDs<LEDS>,
         _frame: &FrameState,
     ) -> io::Result<()> {
-        for led in 0..23 {
+        for led in 0..LEDS {
             let colour = ((self.leds[led].powf(3.0) * 255.0) as u8, 0, 0);
             leds.set(side, led, colour);
         }
         Ok(())
     }
 
-    fn step(&mut self, _frame: &FrameState) -> io::Result<()> {
-        for led in 0..22 {
+    fn step(&mut self, frame: &FrameState) -> io::Result<()> {
+        for led in 0..LEDS - 1 {
             self.leds[led] = self.leds[led + 1]
         }
 
-        self.leds[22] = rand::thread_rng().gen_range(0.0, 1.0);
+        self.leds[LEDS - 1] = frame.rng().gen_range(0.0, 1.0);
 
         Ok(())
     }
 }
 
-pub fn create_float_spray<const LEDS: usize>() -> Box<dyn Mode<LEDS>> {
-    Box::new(FloatSpray { leds: [0.0; 23] })
+pub fn create_float_spray<const LEDS: usize>(_rng: &mut StdRng) -> Box<dyn Mode<LEDS>> {
+    Box::new(FloatSpray { leds: [0.0; LEDS] })
 }
@@ -11,6 +11,7 @@ use palette::encoding::pixel::Pixel;
 use palette::Hsv;
 use palette::Srgb;
 
+use rand::rngs::StdRng;
 use rand::Rng;
 
 /// a value in the space we are dithering
@@ -57,25 +58,25 @@ impl Mul<f32> for V {
     }
 }
 
-struct Dither {
+struct Dither<const LEDS: usize> {
     /// This will contain the errors propagated from the previous frame
-    prev_errors: [V; 23],
+    prev_errors: [V; LEDS],
     /// This will contain the errors propagated to the next frame
-    next_errors: [V; 23],
+    next_errors: [V; LEDS],
     /// pre-step will render into here
-    rgb: [RGB24; 23],
+    rgb: [RGB24; LEDS],
     /// selection of pixel colours that can be used
     available_colours: Vec<(f32, f32, f32)>,
 }
 
-impl<const LEDS: usize> Mode<LEDS> for Dither {
+impl<const LEDS: usize> Mode<LEDS> for Dither<LEDS> {
     fn render(
         &self,
         side: leds::Side,
         leds: &mut leds::WheelLEDs<LEDS>,
         _frame: &FrameState,
     ) -> io::Result<()> {
-        for led in 0..23 {
+        for led in 0..LEDS {
             leds.set(side, led, self.rgb[led]);
         }
         Ok(())
@@ -85,18 +86,18 @@ impl<const LEDS: usize> Mode<LEDS> for Dither {
         let bounded_pos = frame.spin_pos.min(1.0);
 
         let mut row_accum_error: V = V { v: (0.0, 0.0, 0.0) };
-        self.next_errors = [V { v: (0.0, 0.0, 0.0) }; 23];
+        self.next_errors = [V { v: (0.0, 0.0, 0.0) }; LEDS];
 
-        for led in 0..23 {
+        for led in 0..LEDS {
             let hue = (bounded_pos * 360.0).min(360.0);
 
-            let value = ((led as f32) / 23.0).powf(2.0) * 0.9 + 0.1;
+            let value = ((led as f32) / (LEDS as f32)).powf(2.0) * 0.9 + 0.1;
             // don't push value too high - if can't render full intensity, colour choosing alg locks on red
             let hsv: Hsv = Hsv::from_components((hue, 1.0, value));
 
             let srgb = Srgb::from(hsv);
 
-            let pixels: [f32; 3] = srgb.into_linear().into_format().into_raw();
+            let pixels: [f32; 3] = srgb.into_format().into_raw();
 
             let r = pixels[0];
             let g = pixels[1];
@@ -122,18 +123,12 @@ impl<const LEDS: usize> Mode<LEDS> for Dither {
                 self.next_errors[led - 1] += lower_accum_error;
             }
             self.next_errors[led] += mid_accum_error;
-            if led < 22 {
+            if led < LEDS - 1 {
                 self.next_errors[led + 1] += higher_accum_error;
             }
 
-            const GAMMA: f32 = 2.0;
-            let v = render_amount;
-            let (r, g, b) = v.v;
-            let colour = (
-                (255.0 * (r.powf(GAMMA))) as u8,
-                (255.0 * (g.powf(GAMMA))) as u8,
-                (255.0 * (b.powf(GAMMA))) as u8,
-            );
+            let (r, g, b) = render_amount.v;
+            let colour = ((255.0 * r) as u8, (255.0 * g) as u8, (255.0 * b) as u8);
             self.rgb[led] = colour;
         }
 
@@ -146,10 +141,10 @@ impl<const LEDS: usize> Mode<LEDS> for Dither {
     }
 }
 
-pub fn create_dither<const LEDS: usize>() -> Box<dyn Mode<LEDS>> {
-    let num_colours = rand::thread_rng().gen_range(2, 6);
+pub fn create_dither<const LEDS: usize>(rng: &mut StdRng) -> Box<dyn Mode<LEDS>> {
+    let num_colours = rng.gen_range(2, 6);
 
-    let base_degrees = rand::thread_rng().gen_range(0.0, 360.0);
+    let base_degrees = rng.gen_range(0.0, 360.0);
     let step_degrees = 360.0 / (num_colours as f32);
 
     let mut colour_vec: Vec<(f32, f32, f32)> = Vec::new();
@@ -161,7 +156,7 @@ pub fn create_dither<const LEDS: usize>() -> Box<dyn Mode<LEDS>> {
 
         let srgb = Srgb::from(hsv);
 
-        let pixels: [f32; 3] = srgb.into_linear().into_format().into_raw();
+        let pixels: [f32; 3] = srgb.into_format().into_raw();
 
         let r = pixels[0];
         let g = pixels[1];
@@ -171,9 +166,9 @@ pub fn create_dither<const LEDS: usize>() -> Box<dyn Mode<LEDS>> {
     }
 
     Box::new(Dither {
-        prev_errors: [V { v: (0.0, 0.0, 0.0) }; 23],
-        next_errors: [V { v: (0.0, 0.0, 0.0) }; 23],
-        rgb: [(0, 0, 0); 23],
+        prev_errors: [V { v: (0.0, 0.0, 0.0) }; LEDS],
+        next_errors: [V { v: (0.0, 0.0, 0.0) }; LEDS],
+        rgb: [(0, 0, 0); LEDS],
         available_colours: colour_vec,
     })
 }
@@ -6,8 +6,9 @@ use std::io;
 use crate::structs::RGB24;
 
 pub fn render_mod_speckle(wheel_leds: &mut [RGB24], framestate: &FrameState) -> io::Result<()> {
-    for led in 0..23 {
-        let m = framestate.loop_counter % (2 + (22 - led) as u32);
+    let leds = wheel_leds.len();
+    for led in 0..leds {
+        let m = framestate.loop_counter % (2 + (leds - 1 - led) as u32);
         if m == 0 {
             wheel_leds[led] = (255, 255, 0);
         } else {
@@ -19,9 +20,10 @@ pub fn render_mod_speckle(wheel_leds: &mut [RGB24], framestate: &FrameState) ->
 }
 
 pub fn render_speckle_onepix(wheel_leds: &mut [RGB24], framestate: &FrameState) -> io::Result<()> {
+    let leds = wheel_leds.len();
     let mut done = false;
-    for led in 0..23 {
-        let m = framestate.loop_counter % (2 + (22 - led) as u32);
+    for led in 0..leds {
+        let m = framestate.loop_counter % (2 + (leds - 1 - led) as u32);
         if m == 0 && !done {
             wheel_leds[led] = (255, 255, 0);
             done = true;
@@ -33,10 +35,10 @@ pub fn render_speckle_onepix(wheel_leds: &mut [RGB24], framestate: &FrameState)
     Ok(())
 }
 
-pub fn render_speckle_random(wheel_leds: &mut [RGB24], _framestate: &FrameState) -> io::Result<()> {
+pub fn render_speckle_random(wheel_leds: &mut [RGB24], framestate: &FrameState) -> io::Result<()> {
     blank_leds(wheel_leds);
-    let rand_led = rand::thread_rng().gen_range(0, 23);
-    let rand_rgb = rand::thread_rng().gen_range(0, 3);
+    let rand_led = framestate.rng().gen_range(0, wheel_leds.len());
+    let rand_rgb = framestate.rng().gen_range(0, 3);
     let colour = match rand_rgb {
         0 => (255, 0, 0),
         1 => (0, 255, 0),
@@ -55,13 +57,16 @@ pub fn render_rainbow_speckle(wheel_leds: &mut [RGB24], framestate: &FrameState)
 
     let phase = framestate.loop_counter % 4;
 
-    if phase == 0 {
-        for n in 0..6 {
-            wheel_leds[n * 4] = colour;
-        }
-    } else if phase == 2 {
-        for n in 0..6 {
-            wheel_leds[n * 4 + 2] = colour;
+    // every fourth LED, alternating between two sets
+    let first = match phase {
+        0 => Some(0),
+        2 => Some(2),
+        _ => None,
+    };
+
+    if let Some(first) = first {
+        for led in (first..wheel_leds.len()).step_by(4) {
+            wheel_leds[led] = colour;
         }
     }
     // otherwise don't set any pixels
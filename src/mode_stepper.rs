@@ -1,5 +1,6 @@
 use crate::leds;
 use crate::structs::{FrameState, Mode};
+use rand::rngs::StdRng;
 use std::io;
 
 struct Stepper {
@@ -30,7 +31,7 @@ impl<const LEDS: usize> Mode<LEDS> for Stepper {
     }
 }
 
-pub fn construct_stepper<const LEDS: usize>() -> Box<dyn Mode<LEDS>> {
+pub fn construct_stepper<const LEDS: usize>(_rng: &mut StdRng) -> Box<dyn Mode<LEDS>> {
     Box::new(Stepper {
         radius: 0,
         last_spin_pos: 0.0,
@@ -0,0 +1,50 @@
+use std::io;
+
+use crate::helpers::blank_leds;
+use crate::leds::Side;
+use crate::structs::{FrameState, Mode, StatelessModeB, RGB24};
+
+pub fn construct_channel_test<const LEDS: usize>() -> Box<dyn Mode<LEDS>> {
+    Box::new(StatelessModeB {
+        render_fn: render_channel_test,
+    })
+}
+
+/// This renders a still pattern for working out the channel order of a
+/// new strip, to go in the channel_order setting of each arm:
+///  * one white LED at the hub for each arm before this one in the
+///    layout, and then one more, to show which [[arm]] table this is
+///  * then red, green and blue bands, from the hub to the rim
+///
+/// A strip which is correct shows red, green, blue. Otherwise the colours
+/// it shows are its channel order.
+pub fn render_channel_test<const LEDS: usize>(
+    side: Side,
+    wheel_leds: &mut [RGB24; LEDS],
+    _framestate: &FrameState,
+) -> io::Result<()> {
+    blank_leds(wheel_leds);
+
+    let markers = (side.0 + 1).min(LEDS);
+    for led in wheel_leds.iter_mut().take(markers) {
+        *led = (64, 64, 64);
+    }
+
+    // leave a gap after the markers, and one between each band
+    let start = markers + 1;
+    if start >= LEDS {
+        return Ok(());
+    }
+    let band = (LEDS - start + 1) / 3;
+
+    let colours: [RGB24; 3] = [(255, 0, 0), (0, 255, 0), (0, 0, 255)];
+    for (n, colour) in colours.iter().enumerate() {
+        let from = start + n * band;
+        let to = (from + band).saturating_sub(1).min(LEDS);
+        for led in wheel_leds.iter_mut().take(to).skip(from) {
+            *led = *colour;
+        }
+    }
+
+    Ok(())
+}
@@ -1,29 +1,30 @@
 use crate::helpers::fraction_to_rgb;
 use crate::leds;
 use crate::structs::{FrameState, Mode, RGB24};
+use rand::rngs::StdRng;
 use rand::Rng;
 use std::io;
 
-struct Trails {
-    leds: [RGB24; 23],
+struct Trails<const LEDS: usize> {
+    leds: [RGB24; LEDS],
 }
 
-impl<const LEDS: usize> Mode<LEDS> for Trails {
+impl<const LEDS: usize> Mode<LEDS> for Trails<LEDS> {
     fn render(
         &self,
         side: leds::Side,
         leds: &mut leds::WheelLEDs<LEDS>,
         _frame: &FrameState,
     ) -> io::Result<()> {
-        for led in 0..23 {
+        for led in 0..LEDS {
             leds.set(side, led, self.leds[led]);
         }
         Ok(())
     }
 
-    fn step(&mut self, _frame: &FrameState) -> io::Result<()> {
-        let led = rand::thread_rng().gen_range(0, 23);
-        let swiz = rand::thread_rng().gen_range(0, 2);
+    fn step(&mut self, frame: &FrameState) -> io::Result<()> {
+        let led = frame.rng().gen_range(0, LEDS);
+        let swiz = frame.rng().gen_range(0, 2);
         if swiz == 0 {
             self.leds[led] = (0, 0, 0);
         } else {
@@ -33,25 +34,25 @@ impl<const LEDS: usize> Mode<LEDS> for Trails {
     }
 }
 
-pub fn construct_white_trails<const LEDS: usize>() -> Box<dyn Mode<LEDS>> {
+pub fn construct_white_trails<const LEDS: usize>(_rng: &mut StdRng) -> Box<dyn Mode<LEDS>> {
     Box::new(Trails {
-        leds: [(0, 0, 0); 23],
+        leds: [(0, 0, 0); LEDS],
     })
 }
 
-struct HueTrails {
+struct HueTrails<const LEDS: usize> {
     trigger_denominator: usize,
-    leds: [(f32, f32); 23],
+    leds: [(f32, f32); LEDS],
 }
 
-impl<const LEDS: usize> Mode<LEDS> for HueTrails {
+impl<const LEDS: usize> Mode<LEDS> for HueTrails<LEDS> {
     fn render(
         &self,
         side: leds::Side,
         leds: &mut leds::WheelLEDs<LEDS>,
         _frame: &FrameState,
     ) -> io::Result<()> {
-        for led in 0..23 {
+        for led in 0..LEDS {
             let (h, v) = self.leds[led];
             leds.set(side, led, fraction_to_rgb(h, Some(v)));
         }
@@ -59,14 +60,14 @@ impl<const LEDS: usize> Mode<LEDS> for HueTrails {
     }
 
     fn step(&mut self, frame: &FrameState) -> io::Result<()> {
-        for led in 0..23 {
+        for led in 0..LEDS {
             let (h, v) = self.leds[led];
             // divisor here is something that looks visually good
             self.leds[led] = (h, v / 1.3);
         }
 
-        if rand::thread_rng().gen_range(0, self.trigger_denominator) == 0 {
-            let led = rand::thread_rng().gen_range(0, 23);
+        if frame.rng().gen_range(0, self.trigger_denominator) == 0 {
+            let led = frame.rng().gen_range(0, LEDS);
             self.leds[led] = (frame.spin_pos, 1.0);
         } // else don't turn on anything
 
@@ -74,16 +75,16 @@ impl<const LEDS: usize> Mode<LEDS> for HueTrails {
     }
 }
 
-pub fn construct_hue_trails<const LEDS: usize>() -> Box<dyn Mode<LEDS>> {
+pub fn construct_hue_trails<const LEDS: usize>(_rng: &mut StdRng) -> Box<dyn Mode<LEDS>> {
     Box::new(HueTrails {
         trigger_denominator: 1,
-        leds: [(0.0, 0.0); 23],
+        leds: [(0.0, 0.0); LEDS],
     })
 }
 
-pub fn construct_hue_trails_sparse<const LEDS: usize>() -> Box<dyn Mode<LEDS>> {
+pub fn construct_hue_trails_sparse<const LEDS: usize>(_rng: &mut StdRng) -> Box<dyn Mode<LEDS>> {
     Box::new(HueTrails {
         trigger_denominator: 5,
-        leds: [(0.0, 0.0); 23],
+        leds: [(0.0, 0.0); LEDS],
     })
 }
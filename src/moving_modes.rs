@@ -1,6 +1,6 @@
 use crate::stateless_mode;
 use crate::stateless_mode_b;
-use crate::structs::Mode;
+use crate::structs::ModeConstructor;
 
 use crate::mode_bitmap_text;
 use crate::mode_cellblobs;
@@ -18,7 +18,7 @@ use crate::mode_speckles;
 use crate::mode_stepper;
 use crate::mode_trails;
 
-pub fn modes<const LEDS: usize>() -> &'static [fn() -> Box<dyn Mode<LEDS>>] {
+pub fn modes<const LEDS: usize>() -> &'static [ModeConstructor<LEDS>] {
     &[
         mode_randomwalk::create_fork_lightning,
         mode_randomwalk::create_lightning,
@@ -0,0 +1,177 @@
+use std::f32::consts::TAU;
+
+use crate::layout::Layout;
+use crate::leds::Side;
+use crate::structs::{RGB24, RGB48};
+
+/// A PovWheel collects what a viewer sees on each face of the wheel,
+/// from all the arms in a layout. Mirrored arms light the face seen from
+/// the left of the bike, and the others the face seen from the right.
+pub struct PovWheel {
+    layout: Layout,
+    left: PovCanvas,
+    right: PovCanvas,
+}
+
+impl PovWheel {
+    pub fn new(layout: Layout, bins: usize) -> PovWheel {
+        let rings = layout.max_leds();
+        PovWheel {
+            layout,
+            left: PovCanvas::new(rings, bins),
+            right: PovCanvas::new(rings, bins),
+        }
+    }
+
+    pub fn fade(&mut self, factor: f32) {
+        self.left.fade(factor);
+        self.right.fade(factor);
+    }
+
+    /// Adds the light from a frame, as it was given to a sink, while the
+    /// magnet turns from angle from to angle to, in revolutions.
+    pub fn sweep(&mut self, from: f64, to: f64, frame: &[RGB48]) {
+        let rings = self.layout.max_leds();
+        for (n, arm) in self.layout.arms.iter().enumerate() {
+            let arm_leds = self.layout.frame_arm(frame, Side(n));
+
+            // spread arms with fewer LEDs out to the rim
+            let leds: Vec<RGB48> = (0..rings)
+                .map(|ring| arm_leds[ring * arm_leds.len() / rings])
+                .collect();
+
+            let canvas = if arm.mirrored {
+                &mut self.left
+            } else {
+                &mut self.right
+            };
+            canvas.sweep(
+                (from + arm.angle as f64) as f32,
+                (to + arm.angle as f64) as f32,
+                &leds,
+            );
+        }
+    }
+
+    /// Renders the faces seen from the left and from the right, each as
+    /// described by PovCanvas::render
+    pub fn render(&self, size: usize, hub: f32) -> (Vec<RGB24>, Vec<RGB24>) {
+        (
+            self.left.render(size, hub, true),
+            self.right.render(size, hub, false),
+        )
+    }
+}
+
+/// A PovCanvas accumulates the light from the LEDs on one face of the
+/// wheel, the way a viewer's eye does: in polar coordinates, with one
+/// ring per LED radius, each divided into angular bins.
+pub struct PovCanvas {
+    rings: usize,
+    bins: usize,
+
+    /// light collected for each ring and bin, in the same linear units as
+    /// the 16-bit LED values
+    light: Vec<(f32, f32, f32)>,
+
+    /// how many times an LED has passed over each bin, decayed along with
+    /// light. Light is averaged over passes, so that an LED which is lit
+    /// all the way round shows at its own brightness however fast the
+    /// wheel turns, and a stopped wheel shows the arm itself.
+    passes: Vec<f32>,
+}
+
+impl PovCanvas {
+    pub fn new(rings: usize, bins: usize) -> PovCanvas {
+        PovCanvas {
+            rings,
+            bins,
+            light: vec![(0.0, 0.0, 0.0); rings * bins],
+            passes: vec![0.0; bins],
+        }
+    }
+
+    /// Decays all collected light by factor, modelling the eye slowly
+    /// forgetting what it saw.
+    pub fn fade(&mut self, factor: f32) {
+        for (r, g, b) in self.light.iter_mut() {
+            *r *= factor;
+            *g *= factor;
+            *b *= factor;
+        }
+        for p in self.passes.iter_mut() {
+            *p *= factor;
+        }
+    }
+
+    /// Adds the light from one arm's LEDs, listed hub first, as that arm
+    /// sweeps from angle from to angle to. Angles are measured in
+    /// revolutions, clockwise from the top. An arm which has not moved
+    /// still lights the bin it is in.
+    pub fn sweep(&mut self, from: f32, to: f32, leds: &[RGB48]) {
+        let start = from * self.bins as f32;
+        let end = (to * self.bins as f32).max(start + 1.0);
+
+        let mut bin = start.floor();
+        while bin < end {
+            let overlap = end.min(bin + 1.0) - start.max(bin);
+            let index = (bin as i64).rem_euclid(self.bins as i64) as usize;
+
+            self.passes[index] += overlap;
+
+            for (ring, (r, g, b)) in leds.iter().take(self.rings).enumerate() {
+                let cell = &mut self.light[ring * self.bins + index];
+                cell.0 += *r as f32 * overlap;
+                cell.1 += *g as f32 * overlap;
+                cell.2 += *b as f32 * overlap;
+            }
+
+            bin += 1.0;
+        }
+    }
+
+    /// Renders the canvas into a size by size image, as rows from top to
+    /// bottom. The hub fraction of the radius is left dark. A mirrored
+    /// canvas is drawn as seen from the other side of the wheel, so that
+    /// angles run anticlockwise.
+    pub fn render(&self, size: usize, hub: f32, mirrored: bool) -> Vec<RGB24> {
+        let mut image = vec![(0, 0, 0); size * size];
+        let centre = size as f32 / 2.0;
+
+        for y in 0..size {
+            for x in 0..size {
+                let dx = x as f32 + 0.5 - centre;
+                let dy = y as f32 + 0.5 - centre;
+                let radius = (dx * dx + dy * dy).sqrt() / centre;
+
+                if !(hub..1.0).contains(&radius) {
+                    continue;
+                }
+
+                let ring = (((radius - hub) / (1.0 - hub)) * self.rings as f32) as usize;
+
+                let clockwise = dx.atan2(-dy) / TAU;
+                let angle = if mirrored { -clockwise } else { clockwise };
+                let bin = ((angle.rem_euclid(1.0) * self.bins as f32) as usize).min(self.bins - 1);
+
+                // once passes have faded below one, the light fades too
+                let normalise = 1.0 / self.passes[bin].max(1.0);
+                let (r, g, b) = self.light[ring.min(self.rings - 1) * self.bins + bin];
+
+                image[y * size + x] = (
+                    encode(r * normalise),
+                    encode(g * normalise),
+                    encode(b * normalise),
+                );
+            }
+        }
+
+        image
+    }
+}
+
+/// LED values are linear in light output, but image pixels are not, so
+/// apply a display gamma.
+fn encode(linear: f32) -> u8 {
+    (255.0 * (linear / 65535.0).clamp(0.0, 1.0).powf(1.0 / 2.2)) as u8
+}
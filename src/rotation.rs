@@ -0,0 +1,540 @@
+use std::collections::VecDeque;
+use std::io;
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+
+use crate::magnet::MagnetLayout;
+
+/// The number of recent revolutions that speed and acceleration are
+/// fitted to
+const FIT_REVOLUTIONS: usize = 3;
+
+/// How far short of the next magnet, in revolutions, the angle is held
+/// while waiting for that magnet's pulse
+const SHORTFALL: f64 = 0.001;
+
+/// A gap between pulses longer than this means the wheel has stopped in
+/// between, so the pulses before it are no use for predicting the next
+const FORGET_AFTER: Duration = Duration::from_secs(5);
+
+/// How far out the fit is assumed to be, as a fraction of the gap between
+/// magnets, before there has been a pulse to check its prediction against
+const UNCHECKED_RESIDUAL: f64 = 0.25;
+
+/// Residuals below this mean the fit is good enough to judge whether a
+/// pulse is early, or whether one has been missed
+const TRUSTED_RESIDUAL: f64 = 0.25;
+
+/// A pulse is early, and probably a glitch, if the fit puts the wheel less
+/// than this fraction of the way to the next magnet
+const EARLY_FRACTION: f64 = 0.5;
+
+/// WheelLimits describes the wheel, so that pulses which would mean it was
+/// going impossibly fast can be thrown away
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WheelLimits {
+    /// the distance the bike goes in one revolution, in metres
+    pub circumference_m: f32,
+
+    /// the fastest the bike will ever go, in km/h
+    pub max_speed_kmh: f32,
+}
+
+impl Default for WheelLimits {
+    /// A 20" wheel, which is not going to go faster than 80km/h
+    fn default() -> WheelLimits {
+        WheelLimits {
+            circumference_m: 1.59,
+            max_speed_kmh: 80.0,
+        }
+    }
+}
+
+impl WheelLimits {
+    /// Checks that the limits make sense
+    pub fn validate(&self) -> io::Result<()> {
+        if !(self.circumference_m > 0.0 && self.max_speed_kmh > 0.0) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "wheel circumference_m and max_speed_kmh must be more than 0",
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Counts of what happened to magnet pulses, for diagnostics
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PulseCounts {
+    /// pulses used to estimate the angle
+    pub accepted: u32,
+
+    /// pulses thrown away as glitches, because they came sooner after the
+    /// previous pulse than the wheel can turn, or much sooner than the
+    /// wheel's recent speed says they should
+    pub rejected: u32,
+
+    /// pulses which should have come but did not, and were filled in
+    pub missed: u32,
+}
+
+/// Where the wheel is estimated to be at some moment
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RotationEstimate {
+    /// revolutions past angle 0, from 0 up to but not including 1
+    pub angle: f32,
+
+    /// speed of the wheel in revolutions per second
+    pub angular_velocity: f32,
+
+    /// how much the estimate can be trusted, from 0 (not at all) to 1
+    pub confidence: f32,
+}
+
+/// RotationEstimator predicts the angle of the wheel between magnet
+/// pulses. It fits a constant acceleration through the pulses over the
+/// last few revolutions, so that the angle keeps pace while the wheel
+/// speeds up, and so that while it slows down the angle does not run past
+/// the next magnet.
+pub struct RotationEstimator {
+    magnets: MagnetLayout,
+
+    /// pulses closer together than this are glitches
+    min_interval: Duration,
+
+    /// times of recent pulses, oldest first
+    pulses: VecDeque<Instant>,
+
+    /// the magnet which made the latest pulse, once that can be told
+    magnet: Option<usize>,
+
+    /// the fitted speed at the latest pulse, in revolutions per second
+    velocity: f64,
+
+    /// the fitted acceleration, in revolutions per second per second
+    acceleration: f64,
+
+    /// how far out the previous fit was in predicting the latest pulse, as
+    /// a fraction of the gap between the magnets
+    residual: f64,
+
+    /// true if the previous pulse was thrown away for being early
+    rejected_early: bool,
+
+    counts: PulseCounts,
+}
+
+impl Default for RotationEstimator {
+    fn default() -> RotationEstimator {
+        RotationEstimator::new(MagnetLayout::default(), WheelLimits::default())
+    }
+}
+
+impl RotationEstimator {
+    pub fn new(magnets: MagnetLayout, limits: WheelLimits) -> RotationEstimator {
+        // the time for the closest pair of magnets to pass at top speed
+        let min_gap = (0..magnets.len())
+            .map(|m| magnets.gap_before(m))
+            .fold(1.0, f64::min);
+        let max_revs_per_sec = limits.max_speed_kmh as f64 / 3.6 / limits.circumference_m as f64;
+        let min_interval = Duration::from_secs_f64(min_gap / max_revs_per_sec);
+
+        RotationEstimator {
+            pulses: VecDeque::with_capacity(magnets.len() * FIT_REVOLUTIONS + 2),
+            magnets,
+            min_interval,
+            magnet: None,
+            velocity: 0.0,
+            acceleration: 0.0,
+            residual: UNCHECKED_RESIDUAL,
+            rejected_early: false,
+            counts: PulseCounts::default(),
+        }
+    }
+
+    /// Records a magnet passing at time, unless it looks like a glitch.
+    /// Pulses must be given in order. Returns true if the pulse was used.
+    /// If the pulse shows that the one before it was missed, that one is
+    /// filled in at the time the fit says it should have come.
+    pub fn pulse(&mut self, time: Instant) -> bool {
+        if let Some(last) = self.pulses.back() {
+            let interval = time.saturating_duration_since(*last);
+            if interval > FORGET_AFTER {
+                self.pulses.clear();
+                self.magnet = None;
+            } else if interval < self.min_interval {
+                self.counts.rejected += 1;
+                return false;
+            }
+        }
+
+        if let Some(m) = self.trusted_magnet() {
+            let n = self.magnets.len();
+            let next = self.magnets.gap_after(m);
+            let after_next = next + self.magnets.gap_after((m + 1) % n);
+            let beyond = after_next + self.magnets.gap_after((m + 2) % n);
+            let revolutions = self.revolutions_at(time);
+
+            if revolutions < next * EARLY_FRACTION && !self.rejected_early {
+                // if the next one looks early too, the fit is more likely
+                // to be wrong than the pulses
+                self.rejected_early = true;
+                self.counts.rejected += 1;
+                return false;
+            }
+
+            // nearer the magnet after next than the next or the one after
+            if revolutions > (next + after_next) / 2.0 && revolutions < (after_next + beyond) / 2.0
+            {
+                if let Some(missed) = self.time_of(next) {
+                    self.counts.missed += 1;
+                    self.record(missed);
+                }
+            }
+        }
+
+        self.rejected_early = false;
+        self.counts.accepted += 1;
+        self.record(time);
+        true
+    }
+
+    /// The magnet which made the latest pulse, if the fit since it is good
+    /// enough to check new pulses against
+    fn trusted_magnet(&self) -> Option<usize> {
+        match self.magnet {
+            Some(m) if self.pulses.len() >= 2 && self.residual < TRUSTED_RESIDUAL => Some(m),
+            _ => None,
+        }
+    }
+
+    /// Adds a pulse to those the fit is made from
+    fn record(&mut self, time: Instant) {
+        // the previous fit should have put this pulse at the next magnet
+        self.residual = match self.magnet {
+            Some(m) if self.pulses.len() >= 2 => {
+                let gap = self.magnets.gap_after(m);
+                (self.revolutions_at(time) - gap).abs() / gap
+            }
+            _ => UNCHECKED_RESIDUAL,
+        };
+
+        // enough pulses for FIT_REVOLUTIONS whole revolutions
+        self.pulses.push_back(time);
+        if self.pulses.len() > self.magnets.len() * FIT_REVOLUTIONS + 1 {
+            self.pulses.pop_front();
+        }
+
+        let intervals: Vec<f64> = self
+            .pulses
+            .iter()
+            .zip(self.pulses.iter().skip(1))
+            .map(|(a, b)| (*b - *a).as_secs_f64())
+            .collect();
+        self.magnet = self.magnets.identify(&intervals);
+
+        self.fit();
+    }
+
+    /// The time at which the fit says the wheel will have turned
+    /// revolutions past the latest pulse, or None if it stops first
+    fn time_of(&self, revolutions: f64) -> Option<Instant> {
+        let last = *self.pulses.back()?;
+        let (v, a) = (self.velocity, self.acceleration);
+        let t = if a.abs() < 1e-9 {
+            revolutions / v
+        } else {
+            let discriminant = v * v + 2.0 * a * revolutions;
+            if discriminant < 0.0 {
+                return None;
+            }
+            (discriminant.sqrt() - v) / a
+        };
+        if t.is_finite() && t >= 0.0 {
+            Some(last + Duration::from_secs_f64(t))
+        } else {
+            None
+        }
+    }
+
+    /// Fits revolutions = velocity * t + acceleration / 2 * t^2 by least
+    /// squares, with t in seconds from the latest pulse, so that the curve
+    /// goes through the latest pulse at 0 revolutions and earlier pulses
+    /// come at their magnets' angles before it.
+    fn fit(&mut self) {
+        self.velocity = 0.0;
+        self.acceleration = 0.0;
+
+        let (last, magnet) = match (self.pulses.back(), self.magnet) {
+            (Some(l), Some(m)) => (*l, m),
+            _ => return,
+        };
+
+        let n = self.magnets.len();
+        let mut points: Vec<(f64, f64)> = Vec::with_capacity(self.pulses.len());
+        let mut revolutions = 0.0;
+        for (k, t) in self.pulses.iter().rev().enumerate().skip(1) {
+            // the pulse after this one, k - 1 pulses before the latest
+            let after = (magnet + n * k - (k - 1)) % n;
+            revolutions -= self.magnets.gap_before(after);
+            points.push((-(last - *t).as_secs_f64(), revolutions));
+        }
+
+        let (mut sxx, mut sx3, mut sx4, mut sxy, mut sx2y) = (0.0, 0.0, 0.0, 0.0, 0.0);
+        for (x, y) in points.iter() {
+            sxx += x * x;
+            sx3 += x * x * x;
+            sx4 += x * x * x * x;
+            sxy += x * y;
+            sx2y += x * x * y;
+        }
+
+        let det = sxx * sx4 - sx3 * sx3;
+        if points.len() >= 2 && det.abs() > 1e-12 {
+            self.velocity = (sxy * sx4 - sx2y * sx3) / det;
+            self.acceleration = 2.0 * (sxx * sx2y - sx3 * sxy) / det;
+        } else if sxx > 0.0 {
+            // one earlier pulse, so only speed can be fitted
+            self.velocity = sxy / sxx;
+        }
+    }
+
+    /// The seconds after the latest pulse to evaluate the fit at for time.
+    /// When slowing down, the fit would have the wheel stop and then turn
+    /// backwards, so time is held at the point where it stops.
+    fn fit_time(&self, time: Instant) -> f64 {
+        let last = match self.pulses.back() {
+            Some(l) => *l,
+            None => return 0.0,
+        };
+
+        let t = time.saturating_duration_since(last).as_secs_f64();
+        if self.acceleration < 0.0 {
+            t.min((-self.velocity / self.acceleration).max(0.0))
+        } else {
+            t
+        }
+    }
+
+    /// Revolutions since the latest pulse, as predicted by the fit, and
+    /// not limited to before the next magnet
+    fn revolutions_at(&self, time: Instant) -> f64 {
+        let t = self.fit_time(time);
+        self.velocity * t + self.acceleration / 2.0 * t * t
+    }
+
+    /// Estimates where the wheel is at time now. Until there have been
+    /// enough pulses to measure the speed and to tell which magnet is
+    /// which, the angle is 0 with no confidence.
+    pub fn estimate(&self, now: Instant) -> RotationEstimate {
+        let magnet = match self.magnet {
+            Some(m) if self.pulses.len() >= 2 => m,
+            _ => return RotationEstimate::default(),
+        };
+
+        let gap = self.magnets.gap_after(magnet);
+        let revolutions = self.revolutions_at(now);
+        let velocity = (self.velocity + self.acceleration * self.fit_time(now)).max(0.0);
+
+        // confidence falls as the last prediction turns out further out,
+        // and as the wheel runs later than predicted for the next pulse
+        let fit_quality = (1.0 - 2.0 * self.residual).clamp(0.0, 1.0);
+        let overdue = if revolutions > gap {
+            gap / revolutions
+        } else {
+            1.0
+        };
+
+        let since_magnet = revolutions.clamp(0.0, gap - SHORTFALL);
+
+        RotationEstimate {
+            angle: (self.magnets.angle(magnet) + since_magnet).rem_euclid(1.0) as f32,
+            angular_velocity: velocity as f32,
+            confidence: (fit_quality * overdue) as f32,
+        }
+    }
+
+    /// How many pulses have been used, thrown away or filled in so far
+    pub fn pulse_counts(&self) -> PulseCounts {
+        self.counts
+    }
+
+    /// How long the wheel took to turn once, up to the latest pulse, or
+    /// zero if it has not turned once since starting.
+    pub fn last_revolution(&self) -> Duration {
+        let n = self.magnets.len();
+        if self.pulses.len() > n {
+            self.pulses[self.pulses.len() - 1] - self.pulses[self.pulses.len() - 1 - n]
+        } else {
+            Duration::ZERO
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Times at which a wheel with one magnet, passing it at start at v
+    /// revolutions per second and accelerating at a, passes it again, as
+    /// long as it keeps going
+    fn pulse_times(start: Instant, v: f64, a: f64, count: usize) -> Vec<Instant> {
+        (0..count)
+            .map_while(|k| {
+                let revolutions = k as f64;
+                let t = if a == 0.0 {
+                    revolutions / v
+                } else {
+                    let discriminant = v * v + 2.0 * a * revolutions;
+                    if discriminant < 0.0 {
+                        return None;
+                    }
+                    (discriminant.sqrt() - v) / a
+                };
+                Some(start + Duration::from_secs_f64(t))
+            })
+            .collect()
+    }
+
+    fn estimator_after(pulses: &[Instant]) -> RotationEstimator {
+        let mut rotation = RotationEstimator::default();
+        for p in pulses {
+            assert!(rotation.pulse(*p), "pulse rejected");
+        }
+        rotation
+    }
+
+    fn assert_close(actual: f64, expected: f64, what: &str) {
+        assert!(
+            (actual - expected).abs() < 1e-3,
+            "{what} was {actual}, expected {expected}"
+        );
+    }
+
+    fn assert_at(actual: Option<Instant>, expected: Instant) {
+        let actual = actual.expect("a predicted time");
+        let error = if actual > expected {
+            actual - expected
+        } else {
+            expected - actual
+        };
+        assert!(error < Duration::from_micros(100), "out by {error:?}");
+    }
+
+    #[test]
+    fn constant_speed() {
+        let start = Instant::now();
+        let pulses = pulse_times(start, 2.0, 0.0, 8);
+        let rotation = estimator_after(&pulses);
+
+        assert_close(rotation.velocity, 2.0, "velocity");
+        assert_close(rotation.acceleration, 0.0, "acceleration");
+        assert_at(
+            rotation.time_of(1.0),
+            start + Duration::from_millis(8 * 500),
+        );
+        assert_at(
+            rotation.time_of(2.5),
+            start + Duration::from_millis(7 * 500 + 1250),
+        );
+    }
+
+    #[test]
+    fn accelerating() {
+        let start = Instant::now();
+        let pulses = pulse_times(start, 1.0, 0.5, 9);
+        let rotation = estimator_after(&pulses);
+
+        // 1 + 0.5t = sqrt(1 + 2 * 0.5 * 8) at the eighth revolution
+        assert_close(rotation.velocity, 3.0, "velocity");
+        assert_close(rotation.acceleration, 0.5, "acceleration");
+        let next = pulse_times(start, 1.0, 0.5, 10)[9];
+        assert_at(rotation.time_of(1.0), next);
+    }
+
+    #[test]
+    fn braking_to_a_stop() {
+        // stops after 4s, 8 revolutions on
+        let start = Instant::now();
+        let pulses = pulse_times(start, 4.0, -1.0, 8);
+        let rotation = estimator_after(&pulses);
+
+        assert_close(rotation.velocity, 2.0_f64.sqrt(), "velocity");
+        assert_close(rotation.acceleration, -1.0, "acceleration");
+
+        // 7.9 revolutions on at 4 - sqrt(16 - 2 * 7.9) seconds, and it
+        // stops before it gets as far as the magnet again
+        assert_at(
+            rotation.time_of(0.9),
+            start + Duration::from_secs_f64(4.0 - 0.2_f64.sqrt()),
+        );
+        assert_eq!(rotation.time_of(1.1), None);
+
+        // stopped, short of the magnet, rather than turning backwards
+        let estimate = rotation.estimate(start + Duration::from_secs(10));
+        assert_eq!(estimate.angular_velocity, 0.0);
+        assert!(estimate.angle > 0.99 && estimate.angle < 1.0);
+    }
+
+    #[test]
+    fn glitch_pulses_are_rejected() {
+        let start = Instant::now();
+        let pulses = pulse_times(start, 2.0, 0.0, 10);
+        let mut rotation = estimator_after(&pulses[..6]);
+
+        // a bounce sooner than the wheel could turn, then interference a
+        // fifth of the way round
+        assert!(!rotation.pulse(pulses[5] + Duration::from_millis(10)));
+        assert!(!rotation.pulse(pulses[5] + Duration::from_millis(100)));
+
+        for p in &pulses[6..] {
+            assert!(rotation.pulse(*p), "pulse rejected");
+        }
+
+        assert_eq!(
+            rotation.pulse_counts(),
+            PulseCounts {
+                accepted: 10,
+                rejected: 2,
+                missed: 0
+            }
+        );
+        assert_close(rotation.velocity, 2.0, "velocity");
+        assert_at(
+            rotation.time_of(1.0),
+            start + Duration::from_millis(10 * 500),
+        );
+    }
+
+    #[test]
+    fn dropped_pulse_is_filled_in() {
+        let start = Instant::now();
+        let pulses = pulse_times(start, 2.0, 0.0, 10);
+        let mut rotation = estimator_after(&pulses[..5]);
+
+        // pulses[5] never comes
+        assert!(rotation.pulse(pulses[6]));
+        let filled_in = rotation.pulses[rotation.pulses.len() - 2];
+        assert_at(Some(filled_in), pulses[5]);
+
+        for p in &pulses[7..] {
+            assert!(rotation.pulse(*p), "pulse rejected");
+        }
+
+        assert_eq!(
+            rotation.pulse_counts(),
+            PulseCounts {
+                accepted: 9,
+                rejected: 0,
+                missed: 1
+            }
+        );
+        assert_close(rotation.velocity, 2.0, "velocity");
+        assert_at(
+            rotation.time_of(1.0),
+            start + Duration::from_millis(10 * 500),
+        );
+    }
+}
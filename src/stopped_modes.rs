@@ -1,11 +1,14 @@
 use std::io;
 
+use crate::helpers::leds_between;
 use crate::leds::{Side, WheelLEDs};
 use crate::structs::{FrameState, StatelessStoppedMode, RGB24};
 
-const MODE_CHANGE_SEC: u64 = 60;
+/// How long each of the caution modes is shown before the next
+pub const MODE_CHANGE_SEC: u64 = 60;
 
-fn caution_modes<const LEDS: usize>() -> &'static [StatelessStoppedMode<LEDS>] {
+/// The caution modes, in the order they take turns
+pub fn caution_modes<const LEDS: usize>() -> &'static [StatelessStoppedMode<LEDS>] {
     &[
         amber_quarters_fader,
         amber_quarters,
@@ -27,8 +30,26 @@ pub fn render_caution_mode<const LEDS: usize>(
 
     let mode = modes[t];
 
-    mode(Side::Left, wheel_leds, framestate)?;
-    mode(Side::Right, wheel_leds, framestate)?;
+    for side in wheel_leds.sides() {
+        mode(side, wheel_leds, framestate)?;
+    }
+    Ok(())
+}
+
+pub fn render_floodlight_mode<const LEDS: usize>(
+    wheel_leds: &mut WheelLEDs<LEDS>,
+    _framestate: &FrameState,
+) -> io::Result<()> {
+    for side in wheel_leds.sides() {
+        for led in 0..LEDS {
+            wheel_leds.set_linear(side, led, (32, 32, 32));
+        }
+        // override the middle ones with full brightness
+        for led in leds_between(0.39, 0.61, LEDS) {
+            wheel_leds.set(side, led, (255, 255, 255));
+        }
+    }
+
     Ok(())
 }
 
@@ -42,22 +63,22 @@ fn red_yellow_slide<const LEDS: usize>(
     let mut set = |l: usize, col: RGB24| {
         let led = l + this_frame_shift;
         if led < LEDS {
-            wheel_leds.set(side, led, col);
+            wheel_leds.set_linear(side, led, col);
         }
     };
 
     set(0, (0, 0, 0));
 
-    for offset in 1..7 {
+    for offset in leds_between(0.04, 0.3, LEDS) {
         set(offset, (255, 0, 0));
     }
-    for offset in 7..13 {
+    for offset in leds_between(0.3, 0.57, LEDS) {
         set(offset, (0, 0, 0));
     }
-    for offset in 13..19 {
+    for offset in leds_between(0.57, 0.83, LEDS) {
         set(offset, (255, 64, 0));
     }
-    for offset in 19..LEDS {
+    for offset in leds_between(0.83, 1.0, LEDS) {
         set(offset, (0, 0, 0));
     }
 
@@ -73,13 +94,13 @@ fn full_quick_ry_pulse<const LEDS: usize>(
     let now_secs = framestate.now.as_secs();
     let flicker = (now_millis / 25) % 4 == 0 && (now_millis / 250) % 2 == 0;
     let topside = now_secs % 2 == 0;
-    if topside ^ (side == Side::Left) {
+    if topside ^ (side == Side::LEFT) {
         for led in 0..LEDS {
             if flicker {
                 if led % 2 == 0 {
                     wheel_leds.set(side, led, (255, 0, 0));
                 } else {
-                    wheel_leds.set(side, led, (128, 64, 0));
+                    wheel_leds.set_linear(side, led, (128, 64, 0));
                 }
             } else {
                 wheel_leds.set(side, led, (0, 0, 0));
@@ -103,10 +124,10 @@ fn full_quick_pulse<const LEDS: usize>(
     let now_secs = framestate.now.as_secs();
     let flicker = (now_millis / 25) % 4 == 0 && (now_millis / 250) % 2 == 0;
     let topside = now_secs % 2 == 0;
-    if topside ^ (side == Side::Left) {
+    if topside ^ (side == Side::LEFT) {
         for led in 0..LEDS {
             if flicker {
-                wheel_leds.set(side, led, (255, 64, 0));
+                wheel_leds.set_linear(side, led, (255, 64, 0));
             } else {
                 wheel_leds.set(side, led, (0, 0, 0));
             }
@@ -129,26 +150,26 @@ fn red_yellow_centre_pulse<const LEDS: usize>(
     let now_secs = framestate.now.as_secs();
     let flicker = (now_millis / 25) % 4 == 0;
     let topside = now_secs % 2 == 0;
-    for led in 0..2 {
-        wheel_leds.set(side, led, (2, 0, 0));
+    for led in leds_between(0.0, 0.09, LEDS) {
+        wheel_leds.set_linear(side, led, (2, 0, 0));
     }
-    for led in 2..4 {
-        wheel_leds.set(side, led, (8, 0, 0));
+    for led in leds_between(0.09, 0.17, LEDS) {
+        wheel_leds.set_linear(side, led, (8, 0, 0));
     }
-    for led in 4..6 {
-        wheel_leds.set(side, led, (64, 0, 0));
+    for led in leds_between(0.17, 0.26, LEDS) {
+        wheel_leds.set_linear(side, led, (64, 0, 0));
     }
 
-    for led in 6..8 {
+    for led in leds_between(0.26, 0.35, LEDS) {
         wheel_leds.set(side, led, (255, 0, 0));
     }
 
-    for led in 8..9 {
+    for led in leds_between(0.35, 0.39, LEDS) {
         wheel_leds.set(side, led, (0, 0, 0));
     }
 
-    if topside ^ (side == Side::Left) {
-        for led in 9..14 {
+    if topside ^ (side == Side::LEFT) {
+        for led in leds_between(0.39, 0.61, LEDS) {
             if flicker {
                 wheel_leds.set(side, led, (255, 255, 0));
             } else {
@@ -156,24 +177,24 @@ fn red_yellow_centre_pulse<const LEDS: usize>(
             }
         }
     } else {
-        for led in 9..14 {
+        for led in leds_between(0.39, 0.61, LEDS) {
             wheel_leds.set(side, led, (0, 0, 0));
         }
     }
-    for led in 14..15 {
+    for led in leds_between(0.61, 0.65, LEDS) {
         wheel_leds.set(side, led, (0, 0, 0));
     }
-    for led in 15..17 {
+    for led in leds_between(0.65, 0.74, LEDS) {
         wheel_leds.set(side, led, (255, 0, 0));
     }
-    for led in 17..19 {
-        wheel_leds.set(side, led, (64, 0, 0));
+    for led in leds_between(0.74, 0.83, LEDS) {
+        wheel_leds.set_linear(side, led, (64, 0, 0));
     }
-    for led in 19..21 {
-        wheel_leds.set(side, led, (8, 0, 0));
+    for led in leds_between(0.83, 0.91, LEDS) {
+        wheel_leds.set_linear(side, led, (8, 0, 0));
     }
-    for led in 21..LEDS {
-        wheel_leds.set(side, led, (2, 0, 0));
+    for led in leds_between(0.91, 1.0, LEDS) {
+        wheel_leds.set_linear(side, led, (2, 0, 0));
     }
 
     Ok(())
@@ -190,21 +211,21 @@ fn amber_quarters<const LEDS: usize>(
 
     let segs = a / 1000; // range: 0 .. 1
 
-    let flip = (segs == 0) ^ (side == Side::Left);
+    let flip = (segs == 0) ^ (side == Side::LEFT);
 
     if flip {
-        for led in 0..11 {
-            wheel_leds.set(side, led, (255, 64, 0));
+        for led in leds_between(0.0, 0.48, LEDS) {
+            wheel_leds.set_linear(side, led, (255, 64, 0));
         }
-        for led in 11..LEDS {
+        for led in leds_between(0.48, 1.0, LEDS) {
             wheel_leds.set(side, led, (0, 0, 0));
         }
     } else {
-        for led in 0..11 {
+        for led in leds_between(0.0, 0.48, LEDS) {
             wheel_leds.set(side, led, (0, 0, 0));
         }
-        for led in 11..LEDS {
-            wheel_leds.set(side, led, (255, 64, 0));
+        for led in leds_between(0.48, 1.0, LEDS) {
+            wheel_leds.set_linear(side, led, (255, 64, 0));
         }
     }
 
@@ -226,30 +247,33 @@ fn amber_quarters_fader<const LEDS: usize>(
     let updown = cycle / 500;
     let fadecycle = cycle % 500;
 
-    let s2 = (fadecycle * 255 / 500) as u8; // rescale to 0..255
-    let s3 = (fadecycle * 64 / 500) as u8; // rescale to 0..64
+    let s2 = (fadecycle * 65535 / 500) as u16; // rescale to 0..65535
+    let s3 = (fadecycle * 16448 / 500) as u16; // rescale to 0..16448, which is 64 in 8 bits
+
+    // light levels, so that the fade ends at the same amber as the other
+    // caution modes
 
     let on_col = if updown == 0 {
         (s2, s3, 0)
     } else {
-        (255 - s2, 64 - s3, 0)
+        (65535 - s2, 16448 - s3, 0)
     };
 
-    let flip = (quarter == 0) ^ (side == Side::Left);
+    let flip = (quarter == 0) ^ (side == Side::LEFT);
 
     if flip {
-        for led in 0..11 {
-            wheel_leds.set(side, led, on_col);
+        for led in leds_between(0.0, 0.48, LEDS) {
+            wheel_leds.set_linear16(side, led, on_col);
         }
-        for led in 11..LEDS {
+        for led in leds_between(0.48, 1.0, LEDS) {
             wheel_leds.set(side, led, (0, 0, 0));
         }
     } else {
-        for led in 0..11 {
+        for led in leds_between(0.0, 0.48, LEDS) {
             wheel_leds.set(side, led, (0, 0, 0));
         }
-        for led in 11..LEDS {
-            wheel_leds.set(side, led, on_col);
+        for led in leds_between(0.48, 1.0, LEDS) {
+            wheel_leds.set_linear16(side, led, on_col);
         }
     }
 
@@ -263,20 +287,20 @@ fn amber_swap<const LEDS: usize>(
 ) -> io::Result<()> {
     let now_secs = framestate.now.as_secs();
     let topside = now_secs % 2 == 0;
-    for led in 0..9 {
+    for led in leds_between(0.0, 0.39, LEDS) {
         wheel_leds.set(side, led, (0, 0, 0));
     }
 
-    if topside ^ (side == Side::Left) {
-        for led in 9..14 {
-            wheel_leds.set(side, led, (255, 64, 0));
+    if topside ^ (side == Side::LEFT) {
+        for led in leds_between(0.39, 0.61, LEDS) {
+            wheel_leds.set_linear(side, led, (255, 64, 0));
         }
     } else {
-        for led in 9..14 {
+        for led in leds_between(0.39, 0.61, LEDS) {
             wheel_leds.set(side, led, (0, 0, 0));
         }
     }
-    for led in 14..LEDS {
+    for led in leds_between(0.61, 1.0, LEDS) {
         wheel_leds.set(side, led, (0, 0, 0));
     }
 
@@ -294,12 +318,12 @@ fn fade_across<const LEDS: usize>(
     let phase = now_ms % max_phase;
     let led_phase = phase / phase_step_ms;
     for led in 0..LEDS {
-        let state = ((led as u128) > led_phase) ^ (side == Side::Left);
+        let state = ((led as u128) > led_phase) ^ (side == Side::LEFT);
 
         if state {
-            wheel_leds.set(side, led, (255, 64, 0));
+            wheel_leds.set_linear(side, led, (255, 64, 0));
         } else {
-            wheel_leds.set(side, led, (1, 0, 0));
+            wheel_leds.set_linear(side, led, (1, 0, 0));
         }
     }
     Ok(())
@@ -1,10 +1,16 @@
 use crate::leds;
 use crate::leds::Side;
+use rand::rngs::StdRng;
+use std::cell::{RefCell, RefMut};
 use std::io;
 use std::time::Duration;
 
 pub type RGB24 = (u8, u8, u8);
 
+/// A colour with 16 bits per channel, for when 8 bits is not precise
+/// enough, mostly at low brightness.
+pub type RGB48 = (u16, u16, u16);
+
 /// A FrameState contains information about the position and timing of
 /// the bike wheel useful for rendering a frame.
 pub struct FrameState {
@@ -15,16 +21,70 @@ pub struct FrameState {
     /// on each render, regardless of time or wheel rotation.
     pub loop_counter: u32,
 
-    /// An estimate of the current position of the wheel, ranging from 0 to
-    /// approximately 1. This might go above 1 if the bike is slowing down,
-    /// so code needs to accept that.
+    /// An estimate of the angle of the wheel, in revolutions past angle 0
+    /// (where the magnet is, on a wheel with one magnet), ranging from 0 up
+    /// to but not including 1. This is predicted from the speed and
+    /// acceleration over the last few revolutions for when the frame is
+    /// expected to light the LEDs, allowing for how long recent frames have
+    /// taken to render and write out. So it keeps up with a wheel which is
+    /// speeding up and does not run past the next magnet on one which is
+    /// slowing down. Modes which draw at fixed places on the wheel should
+    /// use angle(side) instead.
     pub spin_pos: f32,
 
+    /// The angle of each arm, in layout order. See angle().
+    pub arm_angles: Vec<f32>,
+
+    /// An estimate of the current speed of the wheel, in revolutions per
+    /// second.
+    pub angular_velocity: f32,
+
+    /// How much spin_pos and angular_velocity can be trusted, from 0 (not
+    /// at all, such as before the wheel has turned twice) to 1.
+    pub confidence: f32,
+
     /// An estimate of how long the wheel takes to rotate once, based on the
     /// last full rotation.
     pub spin_length: Duration,
+
+    /// How long recent frames have taken to reach the LEDs after being
+    /// rendered.
+    pub latency: Duration,
+
+    /// The distance the bike goes in one revolution, in metres, from the
+    /// [wheel] table of the config.
+    pub circumference_m: f32,
+
+    /// The mode's random number generator, carried on from the one it
+    /// was constructed with. See rng().
+    pub rng: RefCell<StdRng>,
 }
 
+impl FrameState {
+    /// Where one arm is, in revolutions clockwise from straight up as seen
+    /// by someone looking at the face of the wheel that the arm's LEDs
+    /// face, ranging from 0 up to but not including 1. Mirrored arms are
+    /// seen from the other side of the bike, so their angle runs the other
+    /// way. A mode which draws at these angles puts its picture the same
+    /// way up on each face, with every arm on a face drawing the same
+    /// picture.
+    pub fn angle(&self, side: Side) -> f32 {
+        self.arm_angles[side.0]
+    }
+
+    /// Random numbers for the mode, which it should use rather than
+    /// rand::thread_rng(), so that a mode which was constructed with the
+    /// same seed, and is shown the same frames, does the same thing.
+    pub fn rng(&self) -> RefMut<'_, StdRng> {
+        self.rng.borrow_mut()
+    }
+}
+
+/// Constructs a mode, taking any random numbers it needs to start with
+/// from the generator it is given, which then goes on to be its
+/// FrameState::rng.
+pub type ModeConstructor<const LEDS: usize> = fn(&mut StdRng) -> Box<dyn Mode<LEDS>>;
+
 /// render will be called to render each side
 /// then step will be called to allow any state advancing to happen
 pub trait Mode<const LEDS: usize> {
@@ -57,7 +117,7 @@ impl<const LEDS: usize> Mode<LEDS> for StatelessMode<LEDS> {
         leds: &mut leds::WheelLEDs<{ LEDS }>,
         frame: &FrameState,
     ) -> io::Result<()> {
-        (self.render_fn)(leds.side_slice(side), frame)
+        leds.with_side_slice(side, |slice| (self.render_fn)(slice, frame))
     }
 }
 
@@ -65,7 +125,7 @@ impl<const LEDS: usize> Mode<LEDS> for StatelessMode<LEDS> {
 #[macro_export]
 macro_rules! stateless_mode {
     ( $x:expr ) => {
-        || Box::new(crate::structs::StatelessMode { render_fn: $x })
+        |_| Box::new(crate::structs::StatelessMode { render_fn: $x })
     };
 }
 
@@ -80,7 +140,7 @@ impl<const LEDS: usize> Mode<LEDS> for StatelessModeB<LEDS> {
         leds: &mut leds::WheelLEDs<{ LEDS }>,
         frame: &FrameState,
     ) -> io::Result<()> {
-        (self.render_fn)(side, leds.side_slice_b(side), frame)
+        leds.with_side_slice(side, |slice| (self.render_fn)(side, slice, frame))
     }
 }
 
@@ -89,7 +149,7 @@ impl<const LEDS: usize> Mode<LEDS> for StatelessModeB<LEDS> {
 #[macro_export]
 macro_rules! stateless_mode_b {
     ( $x:expr ) => {
-        || Box::new(crate::structs::StatelessModeB { render_fn: $x })
+        |_| Box::new(crate::structs::StatelessModeB { render_fn: $x })
     };
 }
 
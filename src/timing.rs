@@ -0,0 +1,217 @@
+use std::env;
+use std::time::{Duration, Instant};
+
+use crate::clock::Clock;
+use crate::rotation::RotationEstimator;
+
+/// How much each new render time counts towards the average
+const SMOOTHING: f64 = 0.1;
+
+/// The longest wait for a column, so that buttons and shutdown are still
+/// noticed when the wheel is barely turning
+const MAX_WAIT: Duration = Duration::from_millis(50);
+
+/// LeadTime predicts how long it will be from starting a frame until its
+/// light leaves the LEDs: the time to render and show it, which depends on
+/// the mode, and then the time to write it to the LEDs. Each frame's
+/// FrameState is made for that moment, so that pictures stay in the same
+/// place on the wheel whichever mode is drawing them.
+pub struct LeadTime {
+    /// the smoothed time from starting a frame to show() returning, in
+    /// seconds, or None if no frames have been timed since the last reset
+    render: Option<f64>,
+}
+
+impl Default for LeadTime {
+    fn default() -> LeadTime {
+        LeadTime::new()
+    }
+}
+
+impl LeadTime {
+    pub fn new() -> LeadTime {
+        LeadTime { render: None }
+    }
+
+    /// Forgets the render time, for when the mode changes
+    pub fn reset(&mut self) {
+        self.render = None;
+    }
+
+    /// Records how long a frame took from being started to show()
+    /// returning
+    pub fn rendered(&mut self, took: Duration) {
+        let took = took.as_secs_f64();
+        self.render = Some(match self.render {
+            Some(r) => r + (took - r) * SMOOTHING,
+            None => took,
+        });
+    }
+
+    /// The smoothed time from starting a frame to show() returning
+    pub fn render_time(&self) -> Duration {
+        Duration::from_secs_f64(self.render.unwrap_or(0.0))
+    }
+
+    /// How long after being started a frame's light will leave the LEDs,
+    /// given how long recent frames took from show() to the LEDs
+    pub fn predict(&self, latency: Duration) -> Duration {
+        self.render_time() + latency
+    }
+}
+
+/// FrameSchedule says when frames are rendered. Without either setting,
+/// frames are rendered as fast as they can be, as they always have been.
+/// The settings are read from environment variables.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FrameSchedule {
+    /// the number of frames to render in each revolution while the wheel
+    /// is turning, each for when the wheel reaches the start of a column
+    pub columns: Option<u32>,
+
+    /// the number of frames to render each second while the wheel is
+    /// stopped, or turning too slowly to estimate
+    pub stopped_fps: Option<f32>,
+}
+
+impl FrameSchedule {
+    /// The schedule set by RUSTY_WHEELS_COLUMNS and
+    /// RUSTY_WHEELS_STOPPED_FPS, either of which can be left unset.
+    pub fn from_env() -> FrameSchedule {
+        let mut schedule = FrameSchedule::default();
+
+        if let Ok(v) = env::var("RUSTY_WHEELS_COLUMNS") {
+            schedule.columns = Some(v.parse().expect("parseable RUSTY_WHEELS_COLUMNS"));
+        }
+
+        if let Ok(v) = env::var("RUSTY_WHEELS_STOPPED_FPS") {
+            schedule.stopped_fps = Some(v.parse().expect("parseable RUSTY_WHEELS_STOPPED_FPS"));
+        }
+
+        schedule
+    }
+}
+
+/// FrameScheduler waits before each frame until it is due, according to a
+/// FrameSchedule, on a Clock.
+pub struct FrameScheduler {
+    schedule: FrameSchedule,
+
+    /// when the next frame is due while stopped, once one has been
+    next_tick: Option<Instant>,
+
+    /// when the previous frame was let go while moving, if one has been
+    last_column: Option<Instant>,
+}
+
+impl FrameScheduler {
+    pub fn new(schedule: FrameSchedule) -> FrameScheduler {
+        FrameScheduler {
+            schedule,
+            next_tick: None,
+            last_column: None,
+        }
+    }
+
+    /// Waits until the wheel is predicted to reach the start of the next
+    /// column at the moment a frame started now would light up, lead
+    /// later. If the wheel's speed is not known, this waits as if it were
+    /// stopped. Frames are at least half a column apart, so that they do
+    /// not run flat out while the estimated angle is held back waiting for
+    /// a late magnet.
+    pub fn wait_moving(&mut self, clock: &dyn Clock, rotation: &RotationEstimator, lead: Duration) {
+        let columns = match self.schedule.columns {
+            Some(c) if c > 0 => c,
+            _ => return,
+        };
+
+        let now = clock.now();
+        let estimate = rotation.estimate(now + lead);
+        if estimate.angular_velocity <= 0.0 {
+            self.wait_stopped(clock);
+            return;
+        }
+
+        // no wait is longer than MAX_WAIT, which also keeps a wheel that
+        // has all but stopped from overflowing Duration
+        let max_secs = MAX_WAIT.as_secs_f32();
+        let width = 1.0 / columns as f32;
+        let to_next = width - estimate.angle.rem_euclid(width);
+        let column_time =
+            Duration::from_secs_f32((width / estimate.angular_velocity).min(max_secs));
+        let wait = Duration::from_secs_f32((to_next / estimate.angular_velocity).min(max_secs));
+
+        let mut deadline = now + wait;
+        if let Some(last) = self.last_column {
+            deadline = deadline.max(last + column_time / 2);
+        }
+        clock.sleep_until(deadline.min(now + MAX_WAIT));
+        self.last_column = Some(clock.now());
+    }
+
+    /// Waits until the next frame is due at the stopped frame rate
+    pub fn wait_stopped(&mut self, clock: &dyn Clock) {
+        let fps = match self.schedule.stopped_fps {
+            Some(f) if f > 0.0 => f,
+            _ => return,
+        };
+        let interval = Duration::from_secs_f32(1.0 / fps);
+
+        let tick = self.next_tick.unwrap_or_else(|| clock.now());
+        clock.sleep_until(tick);
+
+        // after falling behind, start again from now rather than rushing
+        // to catch up
+        let now = clock.now();
+        self.next_tick = Some(if now > tick + interval {
+            now + interval
+        } else {
+            tick + interval
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::clock::ManualClock;
+
+    #[test]
+    fn stopped_frames_tick_at_the_stopped_fps() {
+        let start = Instant::now();
+        let clock = ManualClock::new(start);
+        let mut scheduler = FrameScheduler::new(FrameSchedule {
+            columns: None,
+            stopped_fps: Some(10.0),
+        });
+        let interval = Duration::from_secs_f32(1.0 / 10.0);
+
+        // the first frame is not held back, and each after it comes a tick
+        // later
+        for n in 0..5 {
+            scheduler.wait_stopped(&clock);
+            assert_eq!(clock.now(), start + interval * n);
+        }
+
+        // a frame which overruns the next tick is followed straight away
+        // by another, and the ticks start again from then
+        clock.advance(Duration::from_millis(250));
+        let late = clock.now();
+        scheduler.wait_stopped(&clock);
+        assert_eq!(clock.now(), late);
+        scheduler.wait_stopped(&clock);
+        assert_eq!(clock.now(), late + interval);
+    }
+
+    #[test]
+    fn unscheduled_frames_do_not_wait() {
+        let start = Instant::now();
+        let clock = ManualClock::new(start);
+        let mut scheduler = FrameScheduler::new(FrameSchedule::default());
+
+        scheduler.wait_stopped(&clock);
+        scheduler.wait_moving(&clock, &RotationEstimator::default(), Duration::ZERO);
+        assert_eq!(clock.now(), start);
+    }
+}
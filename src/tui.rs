@@ -0,0 +1,326 @@
+use std::cmp;
+use std::io;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crossterm::cursor::{Hide, MoveTo, MoveToNextLine, Show};
+use crossterm::event::{Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::style::{Color, Print, ResetColor, SetBackgroundColor, SetForegroundColor};
+use crossterm::terminal::{Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{event, execute, queue, terminal};
+
+use crate::buttons::Button;
+use crate::layout::Layout;
+use crate::leds::LedSink;
+use crate::magnet::{MagnetLayout, RotationSensor};
+use crate::pov::PovWheel;
+use crate::structs::{RGB24, RGB48};
+
+/// The number of angular steps drawn in the terminal
+const ANGLE_BINS: usize = 256;
+
+/// The fraction of the wheel radius inside the innermost LED
+const HUB_FRACTION: f32 = 0.2;
+
+/// The time for the simulated eye to forget most of what it saw
+const PERSISTENCE: Duration = Duration::from_millis(100);
+
+/// The time between terminal redraws
+const DRAW_INTERVAL: Duration = Duration::from_millis(50);
+
+/// The change in simulated wheel speed for each key press
+const RPM_STEP: f64 = 10.0;
+
+/// The stand-in buttons, in the order of their keys: 1 for GPIO12 and
+/// 2 for GPIO13
+const BUTTONS: usize = 2;
+
+/// A Tui draws the wheel in the terminal, in place of the LEDs, while
+/// keys stand in for the magnet and buttons. The terminal is put back
+/// to normal when the Tui is dropped.
+pub struct Tui {
+    state: Arc<Mutex<TuiState>>,
+}
+
+struct TuiState {
+    shutdown_flag: Arc<AtomicBool>,
+
+    rpm: f64,
+
+    /// simulated revolutions of the wheel, as of updated
+    revolutions: f64,
+    updated: Instant,
+
+    /// the position, in revolutions, of the magnet which last pulsed
+    pulsed_position: f64,
+
+    /// presses waiting to be picked up, for each stand-in button
+    presses: [u32; BUTTONS],
+
+    wheel: PovWheel,
+
+    /// the previous frame, which stays lit from shown until the next
+    /// frame, and the wheel position at that time
+    last_frame: Vec<RGB48>,
+    last_revolutions: f64,
+    shown: Instant,
+
+    drawn: Instant,
+
+    /// the most recent error from reading keys, shown on the status line
+    /// as printing it would break up the drawing
+    key_error: Option<String>,
+}
+
+impl TuiState {
+    /// Moves the simulated wheel on to now
+    fn advance(&mut self) {
+        let now = Instant::now();
+        self.revolutions += self.rpm / 60.0 * (now - self.updated).as_secs_f64();
+        self.updated = now;
+    }
+
+    fn handle_keys(&mut self) -> io::Result<()> {
+        while event::poll(Duration::ZERO)? {
+            if let Event::Key(key) = event::read()? {
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+                match key.code {
+                    KeyCode::Up | KeyCode::Char('+') => {
+                        self.advance();
+                        self.rpm += RPM_STEP;
+                    }
+                    KeyCode::Down | KeyCode::Char('-') => {
+                        self.advance();
+                        self.rpm = (self.rpm - RPM_STEP).max(0.0);
+                    }
+                    KeyCode::Char('0') | KeyCode::Char(' ') => {
+                        self.advance();
+                        self.rpm = 0.0;
+                    }
+                    KeyCode::Char('1') => self.presses[0] += 1,
+                    KeyCode::Char('2') => self.presses[1] += 1,
+                    KeyCode::Char('q') => self.shutdown_flag.store(true, Ordering::Relaxed),
+                    KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        self.shutdown_flag.store(true, Ordering::Relaxed)
+                    }
+                    _ => (),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn draw(&self) -> io::Result<()> {
+        let (cols, rows) = terminal::size()?;
+        // two faces side by side, each a square of half-block characters,
+        // leaving a row for the status line
+        let size = cmp::min(
+            (cols as usize).saturating_sub(1) / 2,
+            (rows as usize).saturating_sub(2) * 2,
+        ) & !1;
+
+        let (left, right) = self.wheel.render(size, HUB_FRACTION);
+        let pixel = |x: usize, y: usize| {
+            if x < size {
+                left[y * size + x]
+            } else {
+                right[y * size + x - size]
+            }
+        };
+
+        let mut out = io::stdout().lock();
+        queue!(out, MoveTo(0, 0))?;
+
+        for row in 0..size / 2 {
+            let mut colours = None;
+            for x in 0..size * 2 {
+                let top = pixel(x, row * 2);
+                let bottom = pixel(x, row * 2 + 1);
+                if colours != Some((top, bottom)) {
+                    queue!(
+                        out,
+                        SetForegroundColor(terminal_colour(top)),
+                        SetBackgroundColor(terminal_colour(bottom))
+                    )?;
+                    colours = Some((top, bottom));
+                }
+                queue!(out, Print('\u{2580}'))?;
+            }
+            queue!(
+                out,
+                ResetColor,
+                Clear(ClearType::UntilNewLine),
+                MoveToNextLine(1)
+            )?;
+        }
+
+        queue!(
+            out,
+            Print(format!(
+                "{:.0} rpm   up/down: speed  0: stop  1/2: GPIO12/GPIO13 buttons  q: quit",
+                self.rpm
+            )),
+            Clear(ClearType::FromCursorDown)
+        )?;
+
+        if let Some(e) = &self.key_error {
+            queue!(
+                out,
+                MoveToNextLine(1),
+                Print(format!("Reading keys returned an error: {e}"))
+            )?;
+        }
+
+        out.flush()
+    }
+}
+
+fn terminal_colour((r, g, b): RGB24) -> Color {
+    Color::Rgb { r, g, b }
+}
+
+impl Tui {
+    /// Takes over the terminal. Quitting sets shutdown_flag.
+    pub fn start(shutdown_flag: Arc<AtomicBool>) -> io::Result<Tui> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stdout(), EnterAlternateScreen, Hide)?;
+
+        let now = Instant::now();
+        Ok(Tui {
+            state: Arc::new(Mutex::new(TuiState {
+                shutdown_flag,
+                rpm: 0.0,
+                revolutions: 0.0,
+                updated: now,
+                pulsed_position: 0.0,
+                presses: [0; BUTTONS],
+                wheel: PovWheel::new(Layout::default(), ANGLE_BINS),
+                last_frame: Vec::new(),
+                last_revolutions: 0.0,
+                shown: now,
+                drawn: now,
+                key_error: None,
+            })),
+        })
+    }
+
+    /// A magnet sensor which pulses as each of the magnets passes on the
+    /// simulated wheel, and which also reads the keyboard each time it is
+    /// checked
+    pub fn magnet(&self, magnets: &MagnetLayout) -> TuiMagnet {
+        TuiMagnet {
+            state: Arc::clone(&self.state),
+            magnets: magnets.clone(),
+        }
+    }
+
+    /// A stand-in for one of the buttons: 0 for GPIO12, 1 for GPIO13
+    pub fn button(&self, n: usize) -> TuiButton {
+        assert!(n < BUTTONS, "button number too large");
+        TuiButton {
+            state: Arc::clone(&self.state),
+            n,
+        }
+    }
+
+    /// A sink which draws frames as seen on a simulated wheel with the
+    /// given layout
+    pub fn sink(&self, layout: &Layout) -> TuiSink {
+        let mut state = self.state.lock().expect("tui lock");
+        state.wheel = PovWheel::new(layout.clone(), ANGLE_BINS);
+        TuiSink {
+            state: Arc::clone(&self.state),
+        }
+    }
+}
+
+impl Drop for Tui {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+pub struct TuiMagnet {
+    state: Arc<Mutex<TuiState>>,
+    magnets: MagnetLayout,
+}
+
+impl RotationSensor for TuiMagnet {
+    fn next_pulse(&mut self) -> Option<Instant> {
+        let mut state = self.state.lock().expect("tui lock");
+        if let Err(e) = state.handle_keys() {
+            state.key_error = Some(e.to_string());
+        }
+        state.advance();
+        let position = self.magnets.next_position(state.pulsed_position);
+        if state.revolutions >= position {
+            state.pulsed_position = position;
+
+            // when the wheel passed the magnet
+            let revs_per_sec = state.rpm / 60.0;
+            let since = if revs_per_sec > 0.0 {
+                Duration::from_secs_f64((state.revolutions - position) / revs_per_sec)
+            } else {
+                Duration::ZERO
+            };
+            Some(state.updated - since)
+        } else {
+            None
+        }
+    }
+}
+
+pub struct TuiButton {
+    state: Arc<Mutex<TuiState>>,
+    n: usize,
+}
+
+impl Button for TuiButton {
+    fn pulsed(&mut self) -> bool {
+        let mut state = self.state.lock().expect("tui lock");
+        if state.presses[self.n] > 0 {
+            state.presses[self.n] -= 1;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+pub struct TuiSink {
+    state: Arc<Mutex<TuiState>>,
+}
+
+impl LedSink for TuiSink {
+    fn write_frame(&mut self, frame: &[RGB48]) -> io::Result<()> {
+        let mut state = self.state.lock().expect("tui lock");
+        state.advance();
+
+        let now = state.updated;
+        let fade = (-(now - state.shown).as_secs_f32() / PERSISTENCE.as_secs_f32()).exp();
+
+        let from = state.last_revolutions.rem_euclid(1.0);
+        let to = from + (state.revolutions - state.last_revolutions);
+
+        let last_frame = std::mem::replace(&mut state.last_frame, frame.to_vec());
+        if !last_frame.is_empty() {
+            state.wheel.fade(fade);
+            state.wheel.sweep(from, to, &last_frame);
+        }
+
+        state.last_revolutions = state.revolutions;
+        state.shown = now;
+
+        if now - state.drawn >= DRAW_INTERVAL {
+            state.drawn = now;
+            state.draw()?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,157 @@
+use std::cell::RefCell;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use rusty_wheels::chill_modes::{self, chill_modes, render_chill_mode};
+use rusty_wheels::clock::MonotonicClock;
+use rusty_wheels::layout::Layout;
+use rusty_wheels::leds::{CaptureSink, WheelLEDs};
+use rusty_wheels::moving_modes::modes;
+use rusty_wheels::stopped_modes::{
+    self, caution_modes, render_caution_mode, render_floodlight_mode,
+};
+use rusty_wheels::structs::FrameState;
+
+fn capture_leds<const LEDS: usize>() -> WheelLEDs<LEDS> {
+    WheelLEDs::with_sink(Layout::default(), Box::new(CaptureSink::new()))
+}
+
+/// The FrameState for a wheel turning three times a second, now after
+/// starting
+fn framestate<const LEDS: usize>(
+    wheel_leds: &WheelLEDs<LEDS>,
+    now: Duration,
+    loop_counter: u32,
+    rng: RefCell<StdRng>,
+) -> FrameState {
+    let spin_pos = (loop_counter as f32 * 0.37).rem_euclid(1.0);
+    FrameState {
+        now,
+        loop_counter,
+        spin_pos,
+        arm_angles: wheel_leds.layout().arm_angles(spin_pos),
+        angular_velocity: 3.0,
+        confidence: 1.0,
+        spin_length: Duration::from_millis(333),
+        latency: Duration::ZERO,
+        circumference_m: 1.59,
+        rng,
+    }
+}
+
+/// Builds every moving mode with LEDS per arm and renders a few frames of
+/// a turning wheel with it
+fn render_all_modes<const LEDS: usize>() {
+    assert!(
+        Path::new("font.bdf").exists(),
+        "the text modes need font.bdf in the crate directory, as described in README.md"
+    );
+
+    for (index, construct) in modes::<LEDS>().iter().enumerate() {
+        let mut wheel_leds = capture_leds::<LEDS>();
+        let mut seeded = StdRng::seed_from_u64(index as u64);
+        let mut mode = construct(&mut seeded);
+        let mut rng = RefCell::new(seeded);
+
+        for frame in 0..50 {
+            let now = Duration::from_millis(frame as u64 * 40);
+            let framestate = framestate(&wheel_leds, now, frame, rng);
+
+            mode.pre_step(&framestate).unwrap();
+            for side in wheel_leds.sides() {
+                mode.render(side, &mut wheel_leds, &framestate)
+                    .unwrap_or_else(|e| panic!("mode {index} with {LEDS} LEDs: {e}"));
+            }
+            mode.step(&framestate).unwrap();
+            wheel_leds.show(&MonotonicClock).unwrap();
+            rng = framestate.rng;
+        }
+    }
+}
+
+type StoppedRender<const LEDS: usize> = fn(&mut WheelLEDs<LEDS>, &FrameState) -> io::Result<()>;
+
+/// Renders a couple of seconds of a stopped mode, starting at each of
+/// count multiples of change_sec, so that each mode it takes turns with
+/// is shown
+fn render_stopped<const LEDS: usize>(
+    name: &str,
+    render: StoppedRender<LEDS>,
+    change_sec: u64,
+    count: usize,
+) {
+    let mut wheel_leds = capture_leds::<LEDS>();
+    let mut rng = RefCell::new(StdRng::seed_from_u64(0));
+
+    for k in 0..count as u64 {
+        for frame in 0..50 {
+            let now = Duration::from_secs(k * change_sec) + Duration::from_millis(frame * 40);
+            let framestate = framestate(&wheel_leds, now, frame as u32, rng);
+
+            render(&mut wheel_leds, &framestate)
+                .unwrap_or_else(|e| panic!("{name} {k} with {LEDS} LEDs: {e}"));
+            wheel_leds.show(&MonotonicClock).unwrap();
+            rng = framestate.rng;
+        }
+    }
+}
+
+fn render_all_stopped_modes<const LEDS: usize>() {
+    render_stopped::<LEDS>(
+        "caution mode",
+        render_caution_mode,
+        stopped_modes::MODE_CHANGE_SEC,
+        caution_modes::<LEDS>().len(),
+    );
+    render_stopped::<LEDS>(
+        "chill mode",
+        render_chill_mode,
+        chill_modes::MODE_CHANGE_SEC,
+        chill_modes::<LEDS>().len(),
+    );
+    render_stopped::<LEDS>("floodlight mode", render_floodlight_mode, 1, 1);
+}
+
+#[test]
+fn modes_render_with_1_led() {
+    render_all_modes::<1>();
+}
+
+#[test]
+fn modes_render_with_7_leds() {
+    render_all_modes::<7>();
+}
+
+#[test]
+fn modes_render_with_23_leds() {
+    render_all_modes::<23>();
+}
+
+#[test]
+fn modes_render_with_36_leds() {
+    render_all_modes::<36>();
+}
+
+#[test]
+fn stopped_modes_render_with_1_led() {
+    render_all_stopped_modes::<1>();
+}
+
+#[test]
+fn stopped_modes_render_with_7_leds() {
+    render_all_stopped_modes::<7>();
+}
+
+#[test]
+fn stopped_modes_render_with_23_leds() {
+    render_all_stopped_modes::<23>();
+}
+
+#[test]
+fn stopped_modes_render_with_36_leds() {
+    render_all_stopped_modes::<36>();
+}